@@ -5,8 +5,10 @@ use tauri_plugin_log::{Target, TargetKind};
 
 pub mod recorder;
 use recorder::commands::{
-    cancel_recording, close_recording_session, enumerate_recording_devices,
-    get_current_recording_id, init_recording_session, start_recording, stop_recording, AppData,
+    add_recording_marker, cancel_recording, close_recording_session, enumerate_recording_devices,
+    get_active_recording_ids, get_input_level, get_recorder_status, init_recording_session,
+    list_recoverable_recordings, pause_recording, read_recording_metadata, recover_recording,
+    resume_recording, start_recording, stop_recording, AppData,
 };
 
 pub mod transcription;
@@ -153,13 +155,21 @@ pub async fn run() {
         write_text,
         simulate_enter_keystroke,
         // Audio recorder commands
-        get_current_recording_id,
+        get_active_recording_ids,
+        get_recorder_status,
         enumerate_recording_devices,
         init_recording_session,
         close_recording_session,
         start_recording,
+        pause_recording,
+        resume_recording,
         stop_recording,
         cancel_recording,
+        add_recording_marker,
+        get_input_level,
+        list_recoverable_recordings,
+        recover_recording,
+        read_recording_metadata,
         transcribe_audio_whisper,
         transcribe_audio_parakeet,
         transcribe_audio_moonshine,
@@ -0,0 +1,116 @@
+use serde::{Deserialize, Serialize};
+
+/// Length of each window silence is measured over
+const WINDOW_MS: u32 = 50;
+
+/// Stop after this much continuous silence - sent from frontend
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SilenceTimeout {
+    /// RMS level in dBFS below which audio counts as silence
+    #[serde(default = "default_threshold_db")]
+    pub threshold_db: f32,
+    /// Silence must last this long before the recording is stopped
+    pub duration_ms: u32,
+}
+
+fn default_threshold_db() -> f32 {
+    -50.0
+}
+
+/// Why the recorder stopped on its own
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum AutoStopReason {
+    MaxDuration,
+    Silence,
+}
+
+/// Watches recorded audio for the session's duration and silence limits.
+/// Only audio written while recording counts; paused time is ignored.
+pub struct AutoStop {
+    channels: usize,
+    max_frames: Option<u64>,
+    silence_frames: Option<u64>,
+    /// Linear RMS below which a window is silent
+    silence_threshold: f32,
+    window_len: usize,
+    window_sum_sq: f32,
+    window_count: usize,
+    recorded_frames: u64,
+    silent_frames: u64,
+}
+
+impl AutoStop {
+    /// `None` when neither limit is set
+    pub fn new(
+        max_duration_seconds: Option<f32>,
+        silence_timeout: Option<SilenceTimeout>,
+        sample_rate: u32,
+        channels: u16,
+    ) -> Option<Self> {
+        if max_duration_seconds.is_none() && silence_timeout.is_none() {
+            return None;
+        }
+
+        let channels = channels.max(1) as usize;
+        Some(Self {
+            channels,
+            max_frames: max_duration_seconds
+                .map(|seconds| (seconds.max(0.0) as f64 * sample_rate as f64) as u64),
+            silence_frames: silence_timeout
+                .as_ref()
+                .map(|timeout| timeout.duration_ms as u64 * sample_rate as u64 / 1000),
+            silence_threshold: silence_timeout
+                .map_or(0.0, |timeout| 10f32.powf(timeout.threshold_db / 20.0)),
+            window_len: ((sample_rate * WINDOW_MS / 1000) as usize).max(1) * channels,
+            window_sum_sq: 0.0,
+            window_count: 0,
+            recorded_frames: 0,
+            silent_frames: 0,
+        })
+    }
+
+    /// Start counting again for a new recording
+    pub fn reset(&mut self) {
+        self.window_sum_sq = 0.0;
+        self.window_count = 0;
+        self.recorded_frames = 0;
+        self.silent_frames = 0;
+    }
+
+    /// Account for a block about to be written. Returns the reason to stop and
+    /// how many of its samples to keep, so the max duration is hit exactly.
+    pub fn process(&mut self, data: &[f32]) -> Option<(AutoStopReason, usize)> {
+        let frames = (data.len() / self.channels) as u64;
+
+        if let Some(max_frames) = self.max_frames {
+            let remaining = max_frames.saturating_sub(self.recorded_frames);
+            if frames >= remaining {
+                self.recorded_frames = max_frames;
+                return Some((AutoStopReason::MaxDuration, remaining as usize * self.channels));
+            }
+        }
+        self.recorded_frames += frames;
+
+        let silence_frames = self.silence_frames?;
+        for &sample in data {
+            self.window_sum_sq += sample * sample;
+            self.window_count += 1;
+            if self.window_count < self.window_len {
+                continue;
+            }
+
+            let rms = (self.window_sum_sq / self.window_count as f32).sqrt();
+            if rms < self.silence_threshold {
+                self.silent_frames += (self.window_count / self.channels) as u64;
+            } else {
+                self.silent_frames = 0;
+            }
+            self.window_sum_sq = 0.0;
+            self.window_count = 0;
+        }
+
+        (self.silent_frames >= silence_frames).then_some((AutoStopReason::Silence, data.len()))
+    }
+}
@@ -1,30 +1,87 @@
-use crate::recorder::recorder::{AudioRecording, RecorderState, Result};
+use crate::recorder::level_meter::InputLevel;
+use crate::recorder::metadata::{self, RecordingMarker, RecordingMetadata};
+use crate::recorder::device::{self, RecordingDevice};
+use crate::recorder::disk_space::DiskSpace;
+use crate::recorder::encoder::RecordingFormat;
+use crate::recorder::error::RecorderError;
+use crate::recorder::recorder::{AudioRecording, RecorderState, Result, SessionOptions};
+use crate::recorder::recovery::{self, RecoverableRecording};
+use crate::recorder::rotation::RecordingSegment;
+use crate::recorder::status::RecorderStatus;
+use std::collections::HashMap;
 use std::path::PathBuf;
-use std::sync::Mutex;
+use std::sync::{Arc, Mutex};
 use tauri::State;
 use log::{debug, info};
 
-/// Application state containing the recorder
+/// Application state containing the recording sessions, keyed by recording ID
 pub struct AppData {
-    pub recorder: Mutex<RecorderState>,
+    sessions: Mutex<HashMap<String, Arc<Mutex<RecorderState>>>>,
 }
 
 impl AppData {
     pub fn new() -> Self {
         Self {
-            recorder: Mutex::new(RecorderState::new()),
+            sessions: Mutex::new(HashMap::new()),
         }
     }
+
+    /// Look up an open session. The registry lock is released before the
+    /// session is used, so slow commands on one session never block another.
+    fn session(&self, recording_id: &str) -> Result<Arc<Mutex<RecorderState>>> {
+        self.find_session(recording_id)?
+            .ok_or_else(|| no_session(recording_id))
+    }
+
+    fn lock_sessions(
+        &self,
+    ) -> Result<std::sync::MutexGuard<'_, HashMap<String, Arc<Mutex<RecorderState>>>>> {
+        self.sessions.lock().map_err(|e| RecorderError::Internal {
+            message: format!("Failed to lock recording sessions: {}", e),
+        })
+    }
+
+    fn find_session(&self, recording_id: &str) -> Result<Option<Arc<Mutex<RecorderState>>>> {
+        Ok(self.lock_sessions()?.get(recording_id).cloned())
+    }
+
+    fn all_sessions(&self) -> Result<Vec<Arc<Mutex<RecorderState>>>> {
+        Ok(self.lock_sessions()?.values().cloned().collect())
+    }
+
+    fn take_session(&self, recording_id: &str) -> Result<Option<Arc<Mutex<RecorderState>>>> {
+        Ok(self.lock_sessions()?.remove(recording_id))
+    }
+
+    /// Files written by every open session, which must not be treated as orphaned
+    fn session_file_paths(&self) -> Result<Vec<PathBuf>> {
+        let mut paths = Vec::new();
+        for session in self.all_sessions()? {
+            paths.extend(lock_recorder(&session)?.get_session_file_path().cloned());
+        }
+        Ok(paths)
+    }
+}
+
+/// Lock a session's recorder
+fn lock_recorder(
+    session: &Mutex<RecorderState>,
+) -> Result<std::sync::MutexGuard<'_, RecorderState>> {
+    session.lock().map_err(|e| RecorderError::Internal {
+        message: format!("Failed to lock recorder: {}", e),
+    })
+}
+
+fn no_session(recording_id: &str) -> RecorderError {
+    RecorderError::NoSession {
+        message: format!("No recording session with ID {}", recording_id),
+    }
 }
 
 #[tauri::command]
-pub async fn enumerate_recording_devices(state: State<'_, AppData>) -> Result<Vec<String>> {
+pub async fn enumerate_recording_devices() -> Result<Vec<RecordingDevice>> {
     debug!("Enumerating recording devices");
-    let recorder = state
-        .recorder
-        .lock()
-        .map_err(|e| format!("Failed to lock recorder: {}", e))?;
-    recorder.enumerate_devices()
+    device::enumerate_devices(&cpal::default_host())
 }
 
 #[tauri::command]
@@ -33,12 +90,13 @@ pub async fn init_recording_session(
     recording_id: String,
     output_folder: String,
     sample_rate: Option<u32>,
+    options: Option<SessionOptions>,
     state: State<'_, AppData>,
-    _app_handle: tauri::AppHandle,
-) -> Result<()> {
+    app_handle: tauri::AppHandle,
+) -> Result<Option<DiskSpace>> {
     info!(
-        "Initializing recording session: device={}, id={}, folder={}, sample_rate={:?}",
-        device_identifier, recording_id, output_folder, sample_rate
+        "Initializing recording session: device={}, id={}, folder={}, sample_rate={:?}, options={:?}",
+        device_identifier, recording_id, output_folder, sample_rate, options
     );
 
     // Use the provided output folder
@@ -46,72 +104,211 @@ pub async fn init_recording_session(
 
     // Create the directory if it doesn't exist
     if !recordings_dir.exists() {
-        std::fs::create_dir_all(&recordings_dir)
-            .map_err(|e| format!("Failed to create output folder: {}", e))?;
+        std::fs::create_dir_all(&recordings_dir).map_err(|e| {
+            RecorderError::disk_write(e, "Failed to create output folder".to_string())
+        })?;
     }
 
     // Validate it's a directory (not a file)
     if !recordings_dir.is_dir() {
-        return Err(format!(
-            "Output path is not a directory: {:?}",
-            recordings_dir
-        ));
+        return Err(RecorderError::DiskWrite {
+            message: format!("Output path is not a directory: {:?}", recordings_dir),
+        });
+    }
+
+    // Re-initializing an ID replaces its session; other sessions keep running
+    if let Some(existing) = state.take_session(&recording_id)? {
+        lock_recorder(&existing)?.close_session()?;
     }
 
     // Initialize the session with optional sample rate
-    let mut recorder = state
-        .recorder
-        .lock()
-        .map_err(|e| format!("Failed to lock recorder: {}", e))?;
-    recorder.init_session(device_identifier, recordings_dir, recording_id, sample_rate)
+    let mut recorder = RecorderState::new();
+    recorder.init_session(
+        device_identifier,
+        recordings_dir,
+        recording_id.clone(),
+        sample_rate,
+        options.unwrap_or_default(),
+        app_handle,
+    )?;
+    // How much audio still fits, so the UI can warn before the disk fills up
+    let disk_space = recorder.disk_space();
+    state
+        .lock_sessions()?
+        .insert(recording_id, Arc::new(Mutex::new(recorder)));
+    Ok(disk_space)
 }
 
 #[tauri::command]
-pub async fn start_recording(state: State<'_, AppData>) -> Result<()> {
-    info!("Starting recording");
-    let mut recorder = state
-        .recorder
-        .lock()
-        .map_err(|e| format!("Failed to lock recorder: {}", e))?;
+pub async fn start_recording(recording_id: String, state: State<'_, AppData>) -> Result<()> {
+    info!("Starting recording: {}", recording_id);
+    let session = state.session(&recording_id)?;
+    let mut recorder = lock_recorder(&session)?;
     recorder.start_recording()
 }
 
 #[tauri::command]
-pub async fn stop_recording(state: State<'_, AppData>) -> Result<AudioRecording> {
-    info!("Stopping recording");
-    let mut recorder = state
-        .recorder
-        .lock()
-        .map_err(|e| format!("Failed to lock recorder: {}", e))?;
-    recorder.stop_recording()
+pub async fn pause_recording(recording_id: String, state: State<'_, AppData>) -> Result<()> {
+    info!("Pausing recording: {}", recording_id);
+    let session = state.session(&recording_id)?;
+    let mut recorder = lock_recorder(&session)?;
+    recorder.pause_recording()
 }
 
 #[tauri::command]
-pub async fn cancel_recording(state: State<'_, AppData>) -> Result<()> {
-    info!("Cancelling recording");
-    let mut recorder = state
-        .recorder
-        .lock()
-        .map_err(|e| format!("Failed to lock recorder: {}", e))?;
+pub async fn resume_recording(recording_id: String, state: State<'_, AppData>) -> Result<()> {
+    info!("Resuming recording: {}", recording_id);
+    let session = state.session(&recording_id)?;
+    let mut recorder = lock_recorder(&session)?;
+    recorder.resume_recording()
+}
+
+#[tauri::command]
+pub async fn stop_recording(
+    recording_id: String,
+    state: State<'_, AppData>,
+) -> Result<AudioRecording> {
+    info!("Stopping recording: {}", recording_id);
+    let session = state.session(&recording_id)?;
+    let (mut recording, streaming) = {
+        let mut recorder = lock_recorder(&session)?;
+        (recorder.stop_recording()?, recorder.streaming())
+    };
+    // The writer flushed the last chunk on stop, so this only waits for it to be
+    // transcribed - without holding up other commands for the session
+    recording.transcript = streaming.and_then(|s| s.finish());
+    Ok(recording)
+}
+
+/// Flag the current moment of a recording, optionally with a label
+#[tauri::command]
+pub async fn add_recording_marker(
+    recording_id: String,
+    label: Option<String>,
+    state: State<'_, AppData>,
+) -> Result<RecordingMarker> {
+    debug!("Adding marker to recording {}: {:?}", recording_id, label);
+    let session = state.session(&recording_id)?;
+    let mut recorder = lock_recorder(&session)?;
+    recorder.add_marker(label)
+}
+
+#[tauri::command]
+pub async fn cancel_recording(recording_id: String, state: State<'_, AppData>) -> Result<()> {
+    info!("Cancelling recording: {}", recording_id);
+    // Cancelling also closes the session, so it leaves the registry
+    let session = state
+        .take_session(&recording_id)?
+        .ok_or_else(|| no_session(&recording_id))?;
+    let mut recorder = lock_recorder(&session)?;
     recorder.cancel_recording()
 }
 
 #[tauri::command]
-pub async fn close_recording_session(state: State<'_, AppData>) -> Result<()> {
-    info!("Closing recording session");
-    let mut recorder = state
-        .recorder
-        .lock()
-        .map_err(|e| format!("Failed to lock recorder: {}", e))?;
-    recorder.close_session()
+pub async fn close_recording_session(
+    recording_id: String,
+    state: State<'_, AppData>,
+) -> Result<()> {
+    info!("Closing recording session: {}", recording_id);
+    match state.take_session(&recording_id)? {
+        Some(session) => lock_recorder(&session)?.close_session(),
+        None => Ok(()), // Already closed
+    }
+}
+
+/// IDs of every session that is currently recording (including while paused)
+#[tauri::command]
+pub async fn get_active_recording_ids(state: State<'_, AppData>) -> Result<Vec<String>> {
+    debug!("Getting active recording IDs");
+    let mut ids = Vec::new();
+    for session in state.all_sessions()? {
+        ids.extend(lock_recorder(&session)?.get_current_recording_id());
+    }
+    ids.sort();
+    Ok(ids)
+}
+
+/// Phase, device, formats and progress of a session; idle if it isn't open
+#[tauri::command]
+pub async fn get_recorder_status(
+    recording_id: String,
+    state: State<'_, AppData>,
+) -> Result<RecorderStatus> {
+    match state.find_session(&recording_id)? {
+        Some(session) => Ok(lock_recorder(&session)?.get_status()),
+        None => Ok(RecorderStatus::idle()),
+    }
+}
+
+#[tauri::command]
+pub async fn get_input_level(
+    recording_id: String,
+    state: State<'_, AppData>,
+) -> Result<Option<InputLevel>> {
+    let session = state.session(&recording_id)?;
+    let recorder = lock_recorder(&session)?;
+    Ok(recorder.get_input_level())
+}
+
+#[tauri::command]
+pub async fn list_recoverable_recordings(
+    output_folder: String,
+    state: State<'_, AppData>,
+) -> Result<Vec<RecoverableRecording>> {
+    debug!("Scanning {} for recoverable recordings", output_folder);
+    let recordings_dir = PathBuf::from(output_folder);
+    if !recordings_dir.is_dir() {
+        return Ok(Vec::new());
+    }
+
+    recovery::list_recoverable_recordings(&recordings_dir, &state.session_file_paths()?)
+}
+
+#[tauri::command]
+pub async fn recover_recording(
+    file_path: String,
+    state: State<'_, AppData>,
+) -> Result<AudioRecording> {
+    info!("Recovering recording: {}", file_path);
+    let path = PathBuf::from(file_path);
+
+    if state.session_file_paths()?.contains(&path) {
+        return Err(RecorderError::InvalidState {
+            message: "Cannot recover the file of an active recording session".to_string(),
+        });
+    }
+
+    let recovered = recovery::recover_recording(&path)?;
+    let segment = RecordingSegment {
+        index: 1,
+        file_path: recovered.file_path.clone(),
+        duration_seconds: recovered.duration_seconds,
+    };
+    Ok(AudioRecording {
+        audio_data: Vec::new(),
+        sample_rate: recovered.sample_rate,
+        channels: recovered.channels,
+        duration_seconds: recovered.duration_seconds,
+        paused_seconds: 0.0,
+        overrun_count: 0,
+        dropped_samples: 0,
+        secondary_overrun_count: None,
+        secondary_dropped_samples: None,
+        format: RecordingFormat::Wav,
+        file_extension: RecordingFormat::Wav.extension().to_string(),
+        file_path: Some(recovered.file_path),
+        raw_file_path: None,
+        transcript: None,
+        markers: Vec::new(),
+        quality: None,
+        segments: vec![segment],
+    })
 }
 
+/// Read the JSON sidecar written beside a recording. Accepts the audio file or
+/// the sidecar itself, so recordings stay self-describing after being moved.
 #[tauri::command]
-pub async fn get_current_recording_id(state: State<'_, AppData>) -> Result<Option<String>> {
-    debug!("Getting current recording ID");
-    let recorder = state
-        .recorder
-        .lock()
-        .map_err(|e| format!("Failed to lock recorder: {}", e))?;
-    Ok(recorder.get_current_recording_id())
+pub async fn read_recording_metadata(file_path: String) -> Result<RecordingMetadata> {
+    debug!("Reading metadata for {}", file_path);
+    metadata::read_metadata(&PathBuf::from(file_path))
 }
@@ -0,0 +1,172 @@
+use crate::recorder::error::RecorderError;
+use crate::recorder::recorder::Result;
+use cpal::traits::{DeviceTrait, HostTrait};
+use cpal::{Device, Host};
+use log::debug;
+use serde::Serialize;
+
+/// Identifier accepted everywhere a device is expected, meaning the host's default input
+pub const DEFAULT_DEVICE_ID: &str = "default";
+
+/// One supported input configuration range
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DeviceConfigRange {
+    pub channels: u16,
+    pub min_sample_rate: u32,
+    pub max_sample_rate: u32,
+    pub sample_format: String,
+}
+
+/// Input device with a stable identifier and its capabilities - returned to frontend
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RecordingDevice {
+    /// `{host}:{name}`, with a `#N` suffix when several devices share a name
+    pub id: String,
+    pub name: String,
+    pub is_default: bool,
+    /// Looks like a monitor/loopback source that captures what's playing, i.e. system audio
+    pub is_monitor: bool,
+    pub host: String,
+    /// Distinct channel counts across all supported configs, ascending
+    pub channel_counts: Vec<u16>,
+    /// Distinct sample formats across all supported configs (e.g. "f32", "i16")
+    pub sample_formats: Vec<String>,
+    pub supported_configs: Vec<DeviceConfigRange>,
+    pub default_sample_rate: Option<u32>,
+    pub default_channels: Option<u16>,
+}
+
+/// Input devices paired with their stable identifiers, in host order.
+///
+/// cpal has no persistent device ID, so one is derived from the host and name.
+/// Devices sharing a name are told apart by their position among namesakes,
+/// which stays the same as long as the host enumerates them in the same order.
+fn input_devices_with_ids(host: &Host) -> Result<Vec<(String, String, Device)>> {
+    let host_name = host.id().name().to_lowercase();
+    let mut devices: Vec<(String, String, Device)> = Vec::new();
+
+    for device in host.input_devices().map_err(|e| RecorderError::Internal {
+        message: format!("Failed to get input devices: {}", e),
+    })? {
+        let Ok(name) = device.name() else {
+            continue;
+        };
+        let namesakes = devices.iter().filter(|(_, n, _)| *n == name).count();
+        let id = if namesakes == 0 {
+            format!("{}:{}", host_name, name)
+        } else {
+            format!("{}:{}#{}", host_name, name, namesakes + 1)
+        };
+        devices.push((id, name, device));
+    }
+
+    Ok(devices)
+}
+
+/// List input devices with their capabilities
+pub fn enumerate_devices(host: &Host) -> Result<Vec<RecordingDevice>> {
+    let default_name = host.default_input_device().and_then(|d| d.name().ok());
+    let host_name = host.id().name().to_string();
+
+    let devices = input_devices_with_ids(host)?
+        .into_iter()
+        .map(|(id, name, device)| {
+            let supported_configs: Vec<DeviceConfigRange> = device
+                .supported_input_configs()
+                .map(|configs| {
+                    configs
+                        .map(|c| DeviceConfigRange {
+                            channels: c.channels(),
+                            min_sample_rate: c.min_sample_rate().0,
+                            max_sample_rate: c.max_sample_rate().0,
+                            sample_format: c.sample_format().to_string(),
+                        })
+                        .collect()
+                })
+                .unwrap_or_else(|e| {
+                    debug!("Failed to query configs for '{}': {}", name, e);
+                    Vec::new()
+                });
+
+            let mut channel_counts: Vec<u16> =
+                supported_configs.iter().map(|c| c.channels).collect();
+            channel_counts.sort_unstable();
+            channel_counts.dedup();
+
+            let mut sample_formats: Vec<String> = Vec::new();
+            for config in &supported_configs {
+                if !sample_formats.contains(&config.sample_format) {
+                    sample_formats.push(config.sample_format.clone());
+                }
+            }
+
+            let default_config = device.default_input_config().ok();
+
+            RecordingDevice {
+                is_default: default_name.as_deref() == Some(name.as_str()),
+                is_monitor: is_monitor_name(&name),
+                host: host_name.clone(),
+                channel_counts,
+                sample_formats,
+                supported_configs,
+                default_sample_rate: default_config.as_ref().map(|c| c.sample_rate().0),
+                default_channels: default_config.as_ref().map(|c| c.channels()),
+                id,
+                name,
+            }
+        })
+        .collect();
+
+    Ok(devices)
+}
+
+/// Find a recording device by stable ID, falling back to a plain name match
+/// so identifiers saved before IDs existed keep working
+pub fn find_device(host: &Host, device_identifier: &str) -> Result<Device> {
+    // Handle "default" device
+    if device_identifier.eq_ignore_ascii_case(DEFAULT_DEVICE_ID) {
+        return host
+            .default_input_device()
+            .ok_or_else(|| RecorderError::DeviceNotFound {
+                message: "No default input device available".to_string(),
+            });
+    }
+
+    let mut devices = input_devices_with_ids(host)?;
+    let index = devices
+        .iter()
+        .position(|(id, _, _)| id == device_identifier)
+        .or_else(|| {
+            devices
+                .iter()
+                .position(|(_, name, _)| name == device_identifier)
+        });
+    if let Some(index) = index {
+        return Ok(devices.swap_remove(index).2);
+    }
+
+    Err(RecorderError::DeviceNotFound {
+        message: format!("Device '{}' not found", device_identifier),
+    })
+}
+
+/// The host's default input device along with its stable ID
+pub fn default_input_device_with_id(host: &Host) -> Option<(String, Device)> {
+    let default_name = host.default_input_device()?.name().ok()?;
+    input_devices_with_ids(host)
+        .ok()?
+        .into_iter()
+        .find(|(_, name, _)| *name == default_name)
+        .map(|(id, _, device)| (id, device))
+}
+
+/// Whether a device name matches how hosts label system audio sources
+/// (PulseAudio/PipeWire "Monitor of ..." and `*.monitor`, Windows "Stereo Mix")
+fn is_monitor_name(name: &str) -> bool {
+    let name = name.to_lowercase();
+    ["monitor", "loopback", "stereo mix"]
+        .iter()
+        .any(|pattern| name.contains(pattern))
+}
@@ -0,0 +1,64 @@
+use serde::Serialize;
+use std::path::Path;
+
+/// Recording is refused when less than this much audio would fit
+pub const MIN_MINUTES_REMAINING: f32 = 1.0;
+
+/// How much more audio fits on the disk at the session's data rate
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DiskSpace {
+    pub available_bytes: u64,
+    /// What the session writes per second, raw copy included
+    pub bytes_per_second: u64,
+    pub estimated_minutes_remaining: f32,
+}
+
+impl DiskSpace {
+    /// Free space on the disk holding `folder`, or `None` if the platform can't tell
+    pub fn check(folder: &Path, bytes_per_second: u64) -> Option<Self> {
+        let available_bytes = available_bytes(folder)?;
+        Some(Self {
+            available_bytes,
+            bytes_per_second,
+            estimated_minutes_remaining: available_bytes as f32
+                / bytes_per_second.max(1) as f32
+                / 60.0,
+        })
+    }
+}
+
+#[cfg(unix)]
+fn available_bytes(folder: &Path) -> Option<u64> {
+    let stat = nix::sys::statvfs::statvfs(folder).ok()?;
+    // Blocks available to unprivileged users, not the root-reserved total
+    Some(stat.blocks_available() as u64 * stat.fragment_size() as u64)
+}
+
+#[cfg(windows)]
+fn available_bytes(folder: &Path) -> Option<u64> {
+    use std::os::windows::ffi::OsStrExt;
+    use windows_sys::Win32::Storage::FileSystem::GetDiskFreeSpaceExW;
+
+    let wide: Vec<u16> = folder
+        .as_os_str()
+        .encode_wide()
+        .chain(std::iter::once(0))
+        .collect();
+    let mut available: u64 = 0;
+    // Quota-aware: counts only what the current user may still write
+    let ok = unsafe {
+        GetDiskFreeSpaceExW(
+            wide.as_ptr(),
+            &mut available,
+            std::ptr::null_mut(),
+            std::ptr::null_mut(),
+        )
+    };
+    (ok != 0).then_some(available)
+}
+
+#[cfg(not(any(unix, windows)))]
+fn available_bytes(_folder: &Path) -> Option<u64> {
+    None
+}
@@ -0,0 +1,453 @@
+use log::error;
+use realfft::num_complex::Complex;
+use realfft::{ComplexToReal, RealFftPlanner, RealToComplex};
+use serde::{Deserialize, Serialize};
+use std::f32::consts::PI;
+use std::sync::Arc;
+
+/// Noise suppression analysis window, rounded up to a power of two
+const NOISE_WINDOW_MS: u32 = 32;
+
+/// How fast the noise estimate may rise, so it follows a fan spinning up
+/// without mistaking sustained speech for noise
+const NOISE_RISE_DB_PER_SEC: f32 = 3.0;
+
+/// Minimum tracking underestimates the noise floor; subtract this much more
+const NOISE_OVERSUBTRACTION: f32 = 3.0;
+
+/// Level and gain smoothing for the AGC
+const AGC_LEVEL_TIME_MS: f32 = 300.0;
+const AGC_GAIN_TIME_MS: f32 = 1000.0;
+const AGC_MAX_GAIN_DB: f32 = 24.0;
+const AGC_MIN_GAIN_DB: f32 = -12.0;
+/// Below this level the AGC holds its gain instead of amplifying silence
+const AGC_GATE_DB: f32 = -50.0;
+
+/// Limiter ceiling (-1 dBFS) and release
+const LIMITER_CEILING: f32 = 0.891;
+const LIMITER_RELEASE_MS: f32 = 100.0;
+
+/// Subfolder of the recordings folder holding the unprocessed copies, which
+/// would otherwise compete with the processed audio for `{recording_id}.*`
+pub const RAW_FOLDER: &str = "raw";
+
+/// Processing applied to captured audio before it is written - sent from frontend
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DspConfig {
+    /// High-pass cutoff in Hz, removing rumble and DC offset; off when absent
+    pub high_pass_hz: Option<f32>,
+    /// Spectral noise suppression for steady background noise (fans, hum, hiss)
+    #[serde(default)]
+    pub noise_suppression: bool,
+    /// Most the noise suppressor attenuates noise-only parts of the spectrum, in dB
+    #[serde(default = "default_noise_reduction_db")]
+    pub noise_reduction_db: f32,
+    /// Automatic gain control followed by a limiter
+    #[serde(default)]
+    pub agc: bool,
+    /// RMS level in dBFS the AGC steers speech towards
+    #[serde(default = "default_agc_target_db")]
+    pub agc_target_db: f32,
+    /// Also write the unprocessed audio to `raw/{recording_id}.{ext}`
+    #[serde(default)]
+    pub keep_raw: bool,
+}
+
+fn default_noise_reduction_db() -> f32 {
+    18.0
+}
+
+fn default_agc_target_db() -> f32 {
+    -20.0
+}
+
+impl DspConfig {
+    fn is_enabled(&self) -> bool {
+        self.high_pass_hz.is_some() || self.noise_suppression || self.agc
+    }
+}
+
+/// High-pass filter, noise suppression and AGC/limiter, in that order, over
+/// interleaved samples. Noise suppression delays its output internally but
+/// compensates for it, so the output lines up with the input sample for sample.
+pub struct DspChain {
+    high_pass: Vec<Biquad>,
+    noise: Option<NoiseSuppressor>,
+    agc: Option<Agc>,
+    scratch: Vec<f32>,
+    output: Vec<f32>,
+}
+
+impl DspChain {
+    /// `None` when the config enables no processing
+    pub fn new(config: &DspConfig, sample_rate: u32, channels: u16) -> Option<Self> {
+        if !config.is_enabled() {
+            return None;
+        }
+
+        let channels = channels.max(1) as usize;
+        let high_pass = config
+            .high_pass_hz
+            .map(|cutoff| vec![Biquad::high_pass(cutoff, sample_rate); channels])
+            .unwrap_or_default();
+
+        Some(Self {
+            high_pass,
+            noise: config.noise_suppression.then(|| {
+                NoiseSuppressor::new(sample_rate, channels, config.noise_reduction_db)
+            }),
+            agc: config
+                .agc
+                .then(|| Agc::new(sample_rate, channels, config.agc_target_db)),
+            scratch: Vec::new(),
+            output: Vec::new(),
+        })
+    }
+
+    /// Process a block of interleaved samples
+    pub fn process(&mut self, data: &[f32]) -> &[f32] {
+        self.scratch.clear();
+        self.scratch.extend_from_slice(data);
+
+        if !self.high_pass.is_empty() {
+            let channels = self.high_pass.len();
+            for frame in self.scratch.chunks_exact_mut(channels) {
+                for (sample, filter) in frame.iter_mut().zip(&mut self.high_pass) {
+                    *sample = filter.process(*sample);
+                }
+            }
+        }
+
+        self.output.clear();
+        match self.noise.as_mut() {
+            Some(noise) => noise.process(&self.scratch, &mut self.output),
+            None => std::mem::swap(&mut self.scratch, &mut self.output),
+        }
+
+        if let Some(agc) = self.agc.as_mut() {
+            agc.process(&mut self.output);
+        }
+
+        &self.output
+    }
+
+    /// Return the audio still held back by noise suppression
+    pub fn flush(&mut self) -> &[f32] {
+        self.output.clear();
+        if let Some(noise) = self.noise.as_mut() {
+            noise.flush(&mut self.output);
+        }
+        if let Some(agc) = self.agc.as_mut() {
+            agc.process(&mut self.output);
+        }
+        &self.output
+    }
+}
+
+/// Second-order IIR section (transposed direct form II)
+#[derive(Clone)]
+struct Biquad {
+    b0: f32,
+    b1: f32,
+    b2: f32,
+    a1: f32,
+    a2: f32,
+    z1: f32,
+    z2: f32,
+}
+
+impl Biquad {
+    /// Butterworth high-pass (RBJ audio EQ cookbook)
+    fn high_pass(cutoff_hz: f32, sample_rate: u32) -> Self {
+        let nyquist = sample_rate as f32 / 2.0;
+        let w0 = 2.0 * PI * cutoff_hz.clamp(1.0, nyquist * 0.9) / sample_rate as f32;
+        let alpha = w0.sin() / (2.0 * std::f32::consts::FRAC_1_SQRT_2);
+        let cos_w0 = w0.cos();
+        let a0 = 1.0 + alpha;
+
+        Self {
+            b0: (1.0 + cos_w0) / 2.0 / a0,
+            b1: -(1.0 + cos_w0) / a0,
+            b2: (1.0 + cos_w0) / 2.0 / a0,
+            a1: -2.0 * cos_w0 / a0,
+            a2: (1.0 - alpha) / a0,
+            z1: 0.0,
+            z2: 0.0,
+        }
+    }
+
+    fn process(&mut self, x: f32) -> f32 {
+        let y = self.b0 * x + self.z1;
+        self.z1 = self.b1 * x - self.a1 * y + self.z2;
+        self.z2 = self.b2 * x - self.a2 * y;
+        y
+    }
+}
+
+/// Per-channel STFT state for the noise suppressor
+struct NoiseChannel {
+    /// Last `fft_len` input samples
+    input: Vec<f32>,
+    /// Overlap-add accumulator for the output
+    overlap: Vec<f32>,
+    /// Smoothed power spectrum and the noise floor tracked under it
+    smoothed: Vec<f32>,
+    noise: Vec<f32>,
+    gains: Vec<f32>,
+    primed: bool,
+}
+
+/// Spectral subtraction with a minimum-tracking noise estimate.
+///
+/// Frames overlap by half with a square-root Hann window on analysis and
+/// synthesis, which reconstructs the input exactly when no gain is applied.
+struct NoiseSuppressor {
+    channels: usize,
+    fft_len: usize,
+    hop: usize,
+    window: Vec<f32>,
+    forward: Arc<dyn RealToComplex<f32>>,
+    inverse: Arc<dyn ComplexToReal<f32>>,
+    forward_scratch: Vec<Complex<f32>>,
+    inverse_scratch: Vec<Complex<f32>>,
+    time: Vec<f32>,
+    spectrum: Vec<Complex<f32>>,
+    states: Vec<NoiseChannel>,
+    /// New frames collected toward the next hop
+    filled: usize,
+    /// Output frames still to drop to cancel the analysis latency
+    skip: usize,
+    gain_floor: f32,
+    /// Per-hop growth factor for the noise estimate (power domain)
+    noise_rise: f32,
+    frames_in: u64,
+    frames_out: u64,
+}
+
+impl NoiseSuppressor {
+    fn new(sample_rate: u32, channels: usize, reduction_db: f32) -> Self {
+        let fft_len = ((sample_rate * NOISE_WINDOW_MS / 1000) as usize).next_power_of_two();
+        let hop = fft_len / 2;
+        let bins = fft_len / 2 + 1;
+
+        let mut planner = RealFftPlanner::<f32>::new();
+        let forward = planner.plan_fft_forward(fft_len);
+        let inverse = planner.plan_fft_inverse(fft_len);
+
+        let window = (0..fft_len)
+            .map(|i| (0.5 - 0.5 * (2.0 * PI * i as f32 / fft_len as f32).cos()).sqrt())
+            .collect();
+
+        let states = (0..channels)
+            .map(|_| NoiseChannel {
+                input: vec![0.0; fft_len],
+                overlap: vec![0.0; fft_len],
+                smoothed: vec![0.0; bins],
+                noise: vec![0.0; bins],
+                gains: vec![1.0; bins],
+                primed: false,
+            })
+            .collect();
+
+        Self {
+            channels,
+            fft_len,
+            hop,
+            window,
+            forward_scratch: forward.make_scratch_vec(),
+            inverse_scratch: inverse.make_scratch_vec(),
+            time: forward.make_input_vec(),
+            spectrum: forward.make_output_vec(),
+            forward,
+            inverse,
+            states,
+            filled: 0,
+            skip: fft_len - hop,
+            gain_floor: 10f32.powf(-reduction_db.abs() / 20.0),
+            noise_rise: 10f32.powf(NOISE_RISE_DB_PER_SEC / 10.0 * hop as f32 / sample_rate as f32),
+            frames_in: 0,
+            frames_out: 0,
+        }
+    }
+
+    fn process(&mut self, data: &[f32], output: &mut Vec<f32>) {
+        let write_pos = self.fft_len - self.hop;
+        for frame in data.chunks_exact(self.channels) {
+            for (state, &sample) in self.states.iter_mut().zip(frame) {
+                state.input[write_pos + self.filled] = sample;
+            }
+            self.filled += 1;
+            self.frames_in += 1;
+
+            if self.filled == self.hop {
+                self.run_frame(output);
+            }
+        }
+    }
+
+    /// Pad with silence until every input frame has come out the other end
+    fn flush(&mut self, output: &mut Vec<f32>) {
+        let start = output.len();
+        let write_pos = self.fft_len - self.hop;
+        while self.frames_out < self.frames_in {
+            for state in &mut self.states {
+                state.input[write_pos + self.filled..].fill(0.0);
+            }
+            self.run_frame(output);
+        }
+
+        let excess = (self.frames_out - self.frames_in) as usize;
+        let keep = ((output.len() - start) / self.channels).saturating_sub(excess);
+        output.truncate(start + keep * self.channels);
+        self.frames_out = self.frames_in;
+    }
+
+    /// Analyze the last `fft_len` samples of each channel and emit one hop
+    fn run_frame(&mut self, output: &mut Vec<f32>) {
+        let scale = 1.0 / self.fft_len as f32;
+        self.filled = 0;
+
+        for state in &mut self.states {
+            for ((t, &x), &w) in self.time.iter_mut().zip(&state.input).zip(&self.window) {
+                *t = x * w;
+            }
+            if let Err(e) = self.forward.process_with_scratch(
+                &mut self.time,
+                &mut self.spectrum,
+                &mut self.forward_scratch,
+            ) {
+                error!("Noise suppression FFT failed: {}", e);
+                state.input.copy_within(self.hop.., 0);
+                continue;
+            }
+
+            for (k, bin) in self.spectrum.iter_mut().enumerate() {
+                let power = bin.norm_sqr();
+                if state.primed {
+                    state.smoothed[k] = 0.9 * state.smoothed[k] + 0.1 * power;
+                    state.noise[k] = if state.smoothed[k] < state.noise[k] {
+                        state.smoothed[k]
+                    } else {
+                        state.noise[k] * self.noise_rise
+                    };
+                } else {
+                    // Assume the recording starts with background noise
+                    state.smoothed[k] = power;
+                    state.noise[k] = power;
+                }
+
+                let gain = (1.0 - NOISE_OVERSUBTRACTION * state.noise[k] / power.max(1e-12))
+                    .max(self.gain_floor);
+                // Smoothing gains over time keeps isolated bins from warbling
+                state.gains[k] = 0.5 * state.gains[k] + 0.5 * gain;
+                *bin *= state.gains[k];
+            }
+            state.primed = true;
+
+            // DC and Nyquist must be purely real for the inverse transform
+            self.spectrum[0].im = 0.0;
+            if let Some(last) = self.spectrum.last_mut() {
+                last.im = 0.0;
+            }
+            if let Err(e) = self.inverse.process_with_scratch(
+                &mut self.spectrum,
+                &mut self.time,
+                &mut self.inverse_scratch,
+            ) {
+                error!("Noise suppression inverse FFT failed: {}", e);
+                state.input.copy_within(self.hop.., 0);
+                continue;
+            }
+
+            for ((acc, &t), &w) in state.overlap.iter_mut().zip(&self.time).zip(&self.window) {
+                *acc += t * w * scale;
+            }
+            state.input.copy_within(self.hop.., 0);
+        }
+
+        for i in 0..self.hop {
+            if self.skip > 0 {
+                self.skip -= 1;
+                continue;
+            }
+            for state in &self.states {
+                output.push(state.overlap[i]);
+            }
+            self.frames_out += 1;
+        }
+
+        for state in &mut self.states {
+            state.overlap.copy_within(self.hop.., 0);
+            let len = state.overlap.len();
+            state.overlap[len - self.hop..].fill(0.0);
+        }
+    }
+}
+
+/// Slow automatic gain control with a peak limiter behind it.
+/// Gain is linked across channels so the stereo image doesn't shift.
+struct Agc {
+    channels: usize,
+    target_power: f32,
+    gate_power: f32,
+    min_gain: f32,
+    max_gain: f32,
+    level_coeff: f32,
+    gain_coeff: f32,
+    release_coeff: f32,
+    power: f32,
+    gain: f32,
+    limiter_envelope: f32,
+}
+
+impl Agc {
+    fn new(sample_rate: u32, channels: usize, target_db: f32) -> Self {
+        let coeff = |time_ms: f32| 1.0 - (-1000.0 / (time_ms * sample_rate as f32)).exp();
+        let db_to_power = |db: f32| 10f32.powf(db / 10.0);
+
+        Self {
+            channels,
+            target_power: db_to_power(target_db),
+            gate_power: db_to_power(AGC_GATE_DB),
+            min_gain: 10f32.powf(AGC_MIN_GAIN_DB / 20.0),
+            max_gain: 10f32.powf(AGC_MAX_GAIN_DB / 20.0),
+            level_coeff: coeff(AGC_LEVEL_TIME_MS),
+            gain_coeff: coeff(AGC_GAIN_TIME_MS),
+            release_coeff: 1.0 - coeff(LIMITER_RELEASE_MS),
+            power: 0.0,
+            gain: 1.0,
+            limiter_envelope: 0.0,
+        }
+    }
+
+    fn process(&mut self, data: &mut [f32]) {
+        for frame in data.chunks_exact_mut(self.channels) {
+            let frame_power =
+                frame.iter().map(|s| s * s).sum::<f32>() / self.channels as f32;
+            self.power += (frame_power - self.power) * self.level_coeff;
+
+            if self.power > self.gate_power {
+                let desired = (self.target_power / self.power)
+                    .sqrt()
+                    .clamp(self.min_gain, self.max_gain);
+                self.gain += (desired - self.gain) * self.gain_coeff;
+            }
+
+            let mut peak = 0.0f32;
+            for sample in frame.iter_mut() {
+                *sample *= self.gain;
+                peak = peak.max(sample.abs());
+            }
+
+            // Instant attack, so the envelope always covers the current peak
+            self.limiter_envelope = peak.max(self.limiter_envelope * self.release_coeff);
+            if self.limiter_envelope > LIMITER_CEILING {
+                let reduction = LIMITER_CEILING / self.limiter_envelope;
+                for sample in frame.iter_mut() {
+                    *sample *= reduction;
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,186 @@
+use crate::recorder::flac_writer::FlacWriter;
+use crate::recorder::opus_writer::{self, OggOpusWriter};
+use crate::recorder::wav_writer::{WavSampleFormat, WavWriter};
+use serde::{Deserialize, Serialize};
+use std::io;
+use std::path::PathBuf;
+
+/// FLAC bit depth for float sessions; f32 capture is quantized to 24-bit, which is
+/// lossless for every real microphone
+const FLAC_BITS_PER_SAMPLE: u16 = 24;
+
+/// Container/codec used for the main recording file
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RecordingFormat {
+    /// 32-bit float WAV
+    #[default]
+    Wav,
+    /// Lossless FLAC. Local transcription needs FFmpeg to decode it.
+    Flac,
+    /// Opus in an Ogg container. Local transcription needs FFmpeg to decode it.
+    Opus,
+}
+
+impl RecordingFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            RecordingFormat::Wav => "wav",
+            RecordingFormat::Flac => "flac",
+            RecordingFormat::Opus => "opus",
+        }
+    }
+
+    /// Upper bound on the bytes written per second of audio, for disk space checks.
+    /// FLAC is counted as uncompressed since speech rarely compresses predictably.
+    pub fn bytes_per_second(
+        &self,
+        sample_rate: u32,
+        channels: u16,
+        sample_format: WavSampleFormat,
+    ) -> u64 {
+        let bits_per_sample = match self {
+            RecordingFormat::Wav => sample_format.bits_per_sample(),
+            RecordingFormat::Flac => flac_bits_per_sample(sample_format),
+            RecordingFormat::Opus => return opus_writer::bytes_per_second(channels),
+        };
+        sample_rate as u64 * channels as u64 * bits_per_sample as u64 / 8
+    }
+}
+
+/// FLAC stores integers, so float sessions are written at `FLAC_BITS_PER_SAMPLE`
+fn flac_bits_per_sample(sample_format: WavSampleFormat) -> u16 {
+    match sample_format {
+        WavSampleFormat::Float32 => FLAC_BITS_PER_SAMPLE,
+        pcm => pcm.bits_per_sample(),
+    }
+}
+
+/// Progressive file writer for any supported recording format.
+/// Every backend shares the same write/finalize lifecycle as `WavWriter`.
+pub enum RecordingWriter {
+    Wav(WavWriter),
+    Flac(FlacWriter),
+    Opus(Box<OggOpusWriter>),
+}
+
+impl RecordingWriter {
+    /// Create the file and write its headers.
+    /// `sample_format` sets the WAV encoding and the FLAC bit depth; Opus ignores it.
+    pub fn new(
+        format: RecordingFormat,
+        file_path: PathBuf,
+        sample_rate: u32,
+        channels: u16,
+        sample_format: WavSampleFormat,
+    ) -> io::Result<Self> {
+        Ok(match format {
+            RecordingFormat::Wav => RecordingWriter::Wav(WavWriter::with_format(
+                file_path,
+                sample_rate,
+                channels,
+                sample_format,
+            )?),
+            RecordingFormat::Flac => RecordingWriter::Flac(FlacWriter::new(
+                file_path,
+                sample_rate,
+                channels,
+                flac_bits_per_sample(sample_format),
+            )?),
+            RecordingFormat::Opus => RecordingWriter::Opus(Box::new(OggOpusWriter::new(
+                file_path,
+                sample_rate,
+                channels,
+            )?)),
+        })
+    }
+
+    pub fn format(&self) -> RecordingFormat {
+        match self {
+            RecordingWriter::Wav(_) => RecordingFormat::Wav,
+            RecordingWriter::Flac(_) => RecordingFormat::Flac,
+            RecordingWriter::Opus(_) => RecordingFormat::Opus,
+        }
+    }
+
+    /// Title tag for formats that carry one; only WAV does so far
+    pub fn set_title(&mut self, title: String) {
+        if let RecordingWriter::Wav(w) = self {
+            w.set_title(title);
+        }
+    }
+
+    /// Embed a marker in the file, for formats with cue points (WAV).
+    /// Every marker is also kept in the recording's sidecar.
+    pub fn add_marker(&mut self, frame: u64, label: String) {
+        if let RecordingWriter::Wav(w) = self {
+            w.add_marker(frame, label);
+        }
+    }
+
+    /// Write interleaved f32 samples
+    pub fn write_samples_f32(&mut self, samples: &[f32]) -> io::Result<()> {
+        match self {
+            RecordingWriter::Wav(w) => w.write_samples_f32(samples),
+            RecordingWriter::Flac(w) => w.write_samples_f32(samples),
+            RecordingWriter::Opus(w) => w.write_samples_f32(samples),
+        }
+    }
+
+    /// Finalize the file so it is complete and playable
+    pub fn finalize(&mut self) -> io::Result<()> {
+        match self {
+            RecordingWriter::Wav(w) => w.finalize(),
+            RecordingWriter::Flac(w) => w.finalize(),
+            RecordingWriter::Opus(w) => w.finalize(),
+        }
+    }
+
+    /// Get the current duration in seconds
+    pub fn get_duration_seconds(&self) -> f32 {
+        match self {
+            RecordingWriter::Wav(w) => w.get_duration_seconds(),
+            RecordingWriter::Flac(w) => w.get_duration_seconds(),
+            RecordingWriter::Opus(w) => w.get_duration_seconds(),
+        }
+    }
+
+    /// Get the file path
+    pub fn get_file_path(&self) -> &PathBuf {
+        match self {
+            RecordingWriter::Wav(w) => w.get_file_path(),
+            RecordingWriter::Flac(w) => w.get_file_path(),
+            RecordingWriter::Opus(w) => w.get_file_path(),
+        }
+    }
+
+    /// Get audio metadata
+    pub fn get_metadata(&self) -> (u32, u16, f32) {
+        match self {
+            RecordingWriter::Wav(w) => w.get_metadata(),
+            RecordingWriter::Flac(w) => w.get_metadata(),
+            RecordingWriter::Opus(w) => w.get_metadata(),
+        }
+    }
+
+    /// Sample rate and channel count `write_samples_f32` expects. Only Opus
+    /// stores something else, mixing more than two channels down to mono.
+    pub fn input_format(&self) -> (u32, u16) {
+        match self {
+            RecordingWriter::Opus(w) => w.input_format(),
+            other => {
+                let (sample_rate, channels, _) = other.get_metadata();
+                (sample_rate, channels)
+            }
+        }
+    }
+
+    /// Flush any buffered data to disk
+    pub fn flush(&mut self) -> io::Result<()> {
+        match self {
+            RecordingWriter::Wav(w) => w.flush(),
+            RecordingWriter::Flac(w) => w.flush(),
+            RecordingWriter::Opus(w) => w.flush(),
+        }
+    }
+}
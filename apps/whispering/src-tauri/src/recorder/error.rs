@@ -0,0 +1,86 @@
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+#[derive(Error, Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "name")]
+pub enum RecorderError {
+    #[error("Device not found: {message}")]
+    DeviceNotFound { message: String },
+
+    #[error("Unsupported config: {message}")]
+    UnsupportedConfig { message: String },
+
+    #[error("Stream build failed: {message}")]
+    StreamBuildFailed { message: String },
+
+    #[error("Permission denied: {message}")]
+    PermissionDenied { message: String },
+
+    #[error("Disk write error: {message}")]
+    DiskWrite { message: String },
+
+    #[error("File read error: {message}")]
+    FileRead { message: String },
+
+    #[error("No session: {message}")]
+    NoSession { message: String },
+
+    #[error("Invalid state: {message}")]
+    InvalidState { message: String },
+
+    #[error("Audio processing error: {message}")]
+    Processing { message: String },
+
+    #[error("Internal error: {message}")]
+    Internal { message: String },
+}
+
+impl RecorderError {
+    /// Classify a failed file operation: permission problems get their own
+    /// variant since the fix (pick another folder, grant access) differs
+    pub fn disk_write(error: std::io::Error, context: String) -> Self {
+        let message = format!("{}: {}", context, error);
+        match error.kind() {
+            std::io::ErrorKind::PermissionDenied => RecorderError::PermissionDenied { message },
+            _ => RecorderError::DiskWrite { message },
+        }
+    }
+}
+
+impl From<cpal::BuildStreamError> for RecorderError {
+    fn from(error: cpal::BuildStreamError) -> Self {
+        let message = format!("Failed to build stream: {}", error);
+        match error {
+            cpal::BuildStreamError::DeviceNotAvailable => RecorderError::DeviceNotFound { message },
+            cpal::BuildStreamError::StreamConfigNotSupported
+            | cpal::BuildStreamError::InvalidArgument => {
+                RecorderError::UnsupportedConfig { message }
+            }
+            cpal::BuildStreamError::BackendSpecific { err } if is_permission_error(&err) => {
+                RecorderError::PermissionDenied { message }
+            }
+            _ => RecorderError::StreamBuildFailed { message },
+        }
+    }
+}
+
+impl From<cpal::PlayStreamError> for RecorderError {
+    fn from(error: cpal::PlayStreamError) -> Self {
+        let message = format!("Failed to start stream: {}", error);
+        match error {
+            cpal::PlayStreamError::DeviceNotAvailable => RecorderError::DeviceNotFound { message },
+            cpal::PlayStreamError::BackendSpecific { err } if is_permission_error(&err) => {
+                RecorderError::PermissionDenied { message }
+            }
+            _ => RecorderError::StreamBuildFailed { message },
+        }
+    }
+}
+
+/// Backends report a denied microphone only through their own error text
+fn is_permission_error(err: &cpal::BackendSpecificError) -> bool {
+    let description = err.description.to_lowercase();
+    ["permission", "denied", "not authorized", "access"]
+        .iter()
+        .any(|needle| description.contains(needle))
+}
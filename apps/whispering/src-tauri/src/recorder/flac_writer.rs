@@ -0,0 +1,564 @@
+use std::fs::File;
+use std::io::{self, BufWriter, Seek, SeekFrom, Write};
+use std::path::PathBuf;
+use std::time::Instant;
+use log::{debug, info};
+
+/// Samples per channel in every frame except the last
+const BLOCK_SIZE: usize = 4096;
+
+/// Highest fixed predictor order defined by the FLAC format
+const MAX_FIXED_ORDER: usize = 4;
+
+/// Largest Rice parameter expressible with 4-bit parameters (15 is the escape code)
+const MAX_RICE_PARAM: u32 = 14;
+
+/// Offset of the STREAMINFO block body ("fLaC" + metadata block header)
+const STREAMINFO_POS: u64 = 8;
+
+/// Lossless FLAC writer with the same progressive-write lifecycle as `WavWriter`.
+///
+/// Each frame is encoded with the cheapest of the constant, verbatim and fixed
+/// (order 0-4) subframe types, using a single Rice partition for the residual.
+/// That gets most of FLAC's compression on speech without an LPC search.
+pub struct FlacWriter {
+    writer: BufWriter<File>,
+    sample_rate: u32,
+    channels: u16,
+    bits_per_sample: u16,
+    /// Interleaved samples waiting for a full block
+    pending: Vec<i32>,
+    frame_number: u64,
+    min_frame_size: u32,
+    max_frame_size: u32,
+    samples_written: u64,
+    finalized: bool,
+    last_header_update: Instant,
+    file_path: PathBuf,
+}
+
+impl FlacWriter {
+    /// Create a new FLAC file and write the stream header
+    pub fn new(
+        file_path: PathBuf,
+        sample_rate: u32,
+        channels: u16,
+        bits_per_sample: u16,
+    ) -> io::Result<Self> {
+        if !(1..=8).contains(&channels) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("FLAC supports 1-8 channels, got {}", channels),
+            ));
+        }
+
+        let file = File::create(&file_path)?;
+        let mut writer = Self {
+            writer: BufWriter::new(file),
+            sample_rate,
+            channels,
+            bits_per_sample,
+            pending: Vec::with_capacity(BLOCK_SIZE * channels as usize),
+            frame_number: 0,
+            min_frame_size: 0,
+            max_frame_size: 0,
+            samples_written: 0,
+            finalized: false,
+            last_header_update: Instant::now(),
+            file_path,
+        };
+
+        writer.writer.write_all(b"fLaC")?;
+        // Last-metadata-block flag + STREAMINFO type, then the 34-byte length
+        writer.writer.write_all(&[0x80, 0x00, 0x00, 34])?;
+        writer.write_streaminfo()?;
+        writer.writer.flush()?;
+
+        info!(
+            "Created FLAC file at {:?}: {}Hz, {} channels, {}-bit",
+            writer.file_path, sample_rate, channels, bits_per_sample
+        );
+
+        Ok(writer)
+    }
+
+    /// Write f32 samples, encoding a frame each time a block fills up
+    pub fn write_samples_f32(&mut self, samples: &[f32]) -> io::Result<()> {
+        if self.finalized {
+            return Err(io::Error::other("FLAC writer already finalized"));
+        }
+
+        let scale = ((1i64 << (self.bits_per_sample - 1)) - 1) as f32;
+        let block_len = BLOCK_SIZE * self.channels as usize;
+
+        for &sample in samples {
+            self.pending
+                .push((sample.clamp(-1.0, 1.0) * scale).round() as i32);
+            if self.pending.len() == block_len {
+                self.encode_pending()?;
+            }
+        }
+
+        self.samples_written += samples.len() as u64;
+
+        // Update headers periodically (every second)
+        if self.last_header_update.elapsed().as_secs() >= 1 {
+            self.update_headers()?;
+            self.last_header_update = Instant::now();
+        }
+
+        Ok(())
+    }
+
+    /// Encode the buffered samples as one frame
+    fn encode_pending(&mut self) -> io::Result<()> {
+        if self.pending.is_empty() {
+            return Ok(());
+        }
+
+        let channels = self.channels as usize;
+        let block_size = self.pending.len() / channels;
+        let bps = self.bits_per_sample as u32;
+
+        let mut bits = BitWriter::default();
+
+        // Frame header
+        bits.write(0b11_1111_1111_1110, 14); // Sync code
+        bits.write(0, 1); // Reserved
+        bits.write(0, 1); // Fixed block size stream
+        let block_size_code = if block_size == BLOCK_SIZE { 12 } else { 7 };
+        bits.write(block_size_code, 4);
+        bits.write(0, 4); // Sample rate: from STREAMINFO
+        bits.write(channels as u64 - 1, 4); // Independent channels
+        bits.write(sample_size_code(bps), 3);
+        bits.write(0, 1); // Reserved
+        bits.write_utf8(self.frame_number);
+        if block_size_code == 7 {
+            bits.write(block_size as u64 - 1, 16);
+        }
+        let header_crc = crc8(bits.bytes());
+        bits.write(header_crc as u64, 8);
+
+        // One subframe per channel
+        let mut channel_samples = Vec::with_capacity(block_size);
+        for ch in 0..channels {
+            channel_samples.clear();
+            channel_samples.extend(self.pending.iter().skip(ch).step_by(channels));
+            write_subframe(&mut bits, &channel_samples, bps);
+        }
+
+        bits.align();
+        let frame_crc = crc16(bits.bytes());
+        bits.write(frame_crc as u64, 16);
+
+        let frame = bits.into_bytes();
+        self.writer.write_all(&frame)?;
+
+        let frame_size = frame.len() as u32;
+        self.min_frame_size = if self.frame_number == 0 {
+            frame_size
+        } else {
+            self.min_frame_size.min(frame_size)
+        };
+        self.max_frame_size = self.max_frame_size.max(frame_size);
+        self.frame_number += 1;
+        self.pending.clear();
+
+        Ok(())
+    }
+
+    fn write_streaminfo(&mut self) -> io::Result<()> {
+        let mut bits = BitWriter::default();
+        bits.write(BLOCK_SIZE as u64, 16); // Minimum block size
+        bits.write(BLOCK_SIZE as u64, 16); // Maximum block size
+        bits.write(self.min_frame_size as u64, 24);
+        bits.write(self.max_frame_size as u64, 24);
+        bits.write(self.sample_rate as u64, 20);
+        bits.write(self.channels as u64 - 1, 3);
+        bits.write(self.bits_per_sample as u64 - 1, 5);
+        let total_frames = self.samples_written / self.channels as u64;
+        bits.write(total_frames >> 32, 4);
+        bits.write(total_frames & 0xFFFF_FFFF, 32);
+        // MD5 signature left as zero ("not computed")
+        for _ in 0..4 {
+            bits.write(0, 32);
+        }
+        self.writer.write_all(&bits.into_bytes())
+    }
+
+    /// Update the STREAMINFO totals
+    fn update_headers(&mut self) -> io::Result<()> {
+        let current_pos = self.writer.stream_position()?;
+
+        self.writer.seek(SeekFrom::Start(STREAMINFO_POS))?;
+        self.write_streaminfo()?;
+
+        // Seek back to end and flush
+        self.writer.seek(SeekFrom::Start(current_pos))?;
+        self.writer.flush()?;
+
+        debug!(
+            "Updated FLAC headers: {} samples written ({:.2} seconds)",
+            self.samples_written,
+            self.get_duration_seconds()
+        );
+
+        Ok(())
+    }
+
+    /// Finalize the FLAC file: encode the last partial block and fix up STREAMINFO
+    pub fn finalize(&mut self) -> io::Result<()> {
+        if !self.finalized {
+            self.encode_pending()?;
+            self.finalized = true;
+        }
+        self.update_headers()?;
+        self.writer.flush()?;
+
+        info!(
+            "Finalized FLAC file {:?}: {} samples, {:.2} seconds",
+            self.file_path,
+            self.samples_written,
+            self.get_duration_seconds()
+        );
+
+        Ok(())
+    }
+
+    /// Get the current duration in seconds
+    pub fn get_duration_seconds(&self) -> f32 {
+        self.samples_written as f32 / (self.sample_rate as f32 * self.channels as f32)
+    }
+
+    /// Get the file path
+    pub fn get_file_path(&self) -> &PathBuf {
+        &self.file_path
+    }
+
+    /// Get audio metadata
+    pub fn get_metadata(&self) -> (u32, u16, f32) {
+        (self.sample_rate, self.channels, self.get_duration_seconds())
+    }
+
+    /// Flush any buffered data to disk
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+}
+
+impl Drop for FlacWriter {
+    fn drop(&mut self) {
+        if let Err(e) = self.finalize() {
+            log::error!("Failed to finalize FLAC file on drop: {}", e);
+        }
+    }
+}
+
+/// Encode one channel of a block with the cheapest subframe type
+fn write_subframe(bits: &mut BitWriter, samples: &[i32], bps: u32) {
+    // Digital silence (or any constant signal) costs a single sample
+    if samples.iter().all(|&s| s == samples[0]) {
+        bits.write(0, 1);
+        bits.write(0b000000, 6);
+        bits.write(0, 1);
+        bits.write_signed(samples[0] as i64, bps);
+        return;
+    }
+
+    let verbatim_bits = samples.len() as u64 * bps as u64;
+    let mut best: Option<(usize, u32, Vec<i32>, u64)> = None;
+
+    for order in 0..=MAX_FIXED_ORDER.min(samples.len() - 1) {
+        let residual = fixed_residual(samples, order);
+        let (rice_param, residual_bits) = best_rice_param(&residual);
+        let total = order as u64 * bps as u64 + 2 + 4 + 4 + residual_bits;
+        if best.as_ref().is_none_or(|(_, _, _, b)| total < *b) {
+            best = Some((order, rice_param, residual, total));
+        }
+    }
+
+    match best {
+        Some((order, rice_param, residual, total)) if total < verbatim_bits => {
+            bits.write(0, 1);
+            bits.write(0b001000 | order as u64, 6);
+            bits.write(0, 1);
+            for &warmup in &samples[..order] {
+                bits.write_signed(warmup as i64, bps);
+            }
+            bits.write(0b00, 2); // Rice coding, 4-bit parameters
+            bits.write(0, 4); // Partition order 0
+            bits.write(rice_param as u64, 4);
+            for &r in &residual {
+                let folded = fold(r);
+                bits.write_unary(folded >> rice_param);
+                bits.write((folded & ((1 << rice_param) - 1)) as u64, rice_param);
+            }
+        }
+        _ => {
+            bits.write(0, 1);
+            bits.write(0b000001, 6);
+            bits.write(0, 1);
+            for &s in samples {
+                bits.write_signed(s as i64, bps);
+            }
+        }
+    }
+}
+
+/// Residual after the FLAC fixed polynomial predictor of the given order
+fn fixed_residual(samples: &[i32], order: usize) -> Vec<i32> {
+    (order..samples.len())
+        .map(|i| {
+            let x = |k: usize| samples[i - k] as i64;
+            let r = match order {
+                0 => x(0),
+                1 => x(0) - x(1),
+                2 => x(0) - 2 * x(1) + x(2),
+                3 => x(0) - 3 * x(1) + 3 * x(2) - x(3),
+                _ => x(0) - 4 * x(1) + 6 * x(2) - 4 * x(3) + x(4),
+            };
+            r as i32
+        })
+        .collect()
+}
+
+/// Rice parameter with the smallest encoded size, and that size in bits
+fn best_rice_param(residual: &[i32]) -> (u32, u64) {
+    (0..=MAX_RICE_PARAM)
+        .map(|k| {
+            let size = residual
+                .iter()
+                .map(|&r| (fold(r) >> k) as u64 + 1 + k as u64)
+                .sum::<u64>();
+            (k, size)
+        })
+        .min_by_key(|&(_, size)| size)
+        .unwrap_or((0, 0))
+}
+
+/// Map signed residuals onto unsigned values (0, -1, 1, -2, ... -> 0, 1, 2, 3, ...)
+fn fold(r: i32) -> u32 {
+    ((r << 1) ^ (r >> 31)) as u32
+}
+
+fn sample_size_code(bps: u32) -> u64 {
+    match bps {
+        8 => 0b001,
+        12 => 0b010,
+        16 => 0b100,
+        20 => 0b101,
+        24 => 0b110,
+        _ => 0b000, // From STREAMINFO
+    }
+}
+
+/// CRC-8 (polynomial 0x07) over the frame header
+fn crc8(data: &[u8]) -> u8 {
+    data.iter().fold(0u8, |mut crc, &byte| {
+        crc ^= byte;
+        for _ in 0..8 {
+            crc = if crc & 0x80 != 0 {
+                (crc << 1) ^ 0x07
+            } else {
+                crc << 1
+            };
+        }
+        crc
+    })
+}
+
+/// CRC-16 (polynomial 0x8005) over the whole frame
+fn crc16(data: &[u8]) -> u16 {
+    data.iter().fold(0u16, |mut crc, &byte| {
+        crc ^= (byte as u16) << 8;
+        for _ in 0..8 {
+            crc = if crc & 0x8000 != 0 {
+                (crc << 1) ^ 0x8005
+            } else {
+                crc << 1
+            };
+        }
+        crc
+    })
+}
+
+/// MSB-first bit packer used to build frames in memory
+#[derive(Default)]
+struct BitWriter {
+    bytes: Vec<u8>,
+    acc: u64,
+    bit_count: u32,
+}
+
+impl BitWriter {
+    /// Write the low `n` bits of `value` (n <= 32)
+    fn write(&mut self, value: u64, n: u32) {
+        if n == 0 {
+            return;
+        }
+        self.acc = (self.acc << n) | (value & ((1u64 << n) - 1));
+        self.bit_count += n;
+        while self.bit_count >= 8 {
+            self.bit_count -= 8;
+            self.bytes.push((self.acc >> self.bit_count) as u8);
+        }
+        self.acc &= (1u64 << self.bit_count) - 1;
+    }
+
+    /// Two's complement value in `n` bits
+    fn write_signed(&mut self, value: i64, n: u32) {
+        self.write(value as u64, n);
+    }
+
+    /// `q` zero bits followed by a one
+    fn write_unary(&mut self, mut q: u32) {
+        while q >= 32 {
+            self.write(0, 32);
+            q -= 32;
+        }
+        self.write(1, q + 1);
+    }
+
+    /// FLAC's UTF-8 style variable length integer for frame numbers
+    fn write_utf8(&mut self, value: u64) {
+        if value < 0x80 {
+            self.write(value, 8);
+            return;
+        }
+        let mut len = 2;
+        while value >= 1 << (5 * len + 1) {
+            len += 1;
+        }
+        let prefix = (0xFF00u64 >> len) & 0xFF;
+        self.write(prefix | (value >> (6 * (len - 1))), 8);
+        for i in (0..len - 1).rev() {
+            self.write(0x80 | ((value >> (6 * i)) & 0x3F), 8);
+        }
+    }
+
+    /// Pad with zero bits to the next byte boundary
+    fn align(&mut self) {
+        if self.bit_count > 0 {
+            self.write(0, 8 - self.bit_count);
+        }
+    }
+
+    /// Completed bytes so far (only meaningful when byte-aligned)
+    fn bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    fn into_bytes(mut self) -> Vec<u8> {
+        self.align();
+        self.bytes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Deterministic test signal: a sine with noise, plus full-scale and silent stretches
+    fn signal(frames: usize, channels: u16) -> Vec<f32> {
+        let mut seed = 0x1234_5678u32;
+        let mut noise = move || {
+            seed = seed.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+            (seed >> 8) as f32 / (1 << 24) as f32 - 0.5
+        };
+        (0..frames)
+            .flat_map(|i| (0..channels).map(move |ch| (i, ch)))
+            .map(|(i, ch)| match (i / 1000) % 4 {
+                0 => 0.0,
+                1 => if (i / 7) % 2 == 0 { 1.0 } else { -1.0 },
+                _ => (i as f32 * 0.05 * (ch + 1) as f32).sin() * 0.6,
+            } + if (i / 1000) % 4 == 3 { noise() * 0.2 } else { 0.0 })
+            .collect()
+    }
+
+    fn quantize(samples: &[f32], bits_per_sample: u16) -> Vec<i32> {
+        let scale = ((1i64 << (bits_per_sample - 1)) - 1) as f32;
+        samples
+            .iter()
+            .map(|&s| (s.clamp(-1.0, 1.0) * scale).round() as i32)
+            .collect()
+    }
+
+    /// Encode `frames` frames in uneven chunks, decode with claxon and compare
+    fn round_trip(sample_rate: u32, channels: u16, bits_per_sample: u16, frames: usize) {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("test.flac");
+        let samples = signal(frames, channels);
+
+        let mut writer =
+            FlacWriter::new(path.clone(), sample_rate, channels, bits_per_sample).unwrap();
+        for chunk in samples.chunks(channels as usize * 1237) {
+            writer.write_samples_f32(chunk).unwrap();
+        }
+        writer.finalize().unwrap();
+        drop(writer);
+
+        let mut reader = claxon::FlacReader::open(&path).unwrap();
+        let info = reader.streaminfo();
+        assert_eq!(info.sample_rate, sample_rate);
+        assert_eq!(info.channels, channels as u32);
+        assert_eq!(info.bits_per_sample, bits_per_sample as u32);
+        // STREAMINFO can't tell an empty stream from an unknown length
+        assert_eq!(info.samples.unwrap_or(0), frames as u64);
+
+        let decoded: Vec<i32> = reader.samples().map(|s| s.unwrap()).collect();
+        assert_eq!(
+            decoded,
+            quantize(&samples, bits_per_sample),
+            "{} Hz, {} channels, {}-bit, {} frames",
+            sample_rate,
+            channels,
+            bits_per_sample,
+            frames
+        );
+    }
+
+    #[test]
+    fn round_trips_common_formats() {
+        for sample_rate in [8000, 16000, 44100, 48000, 96000] {
+            for channels in [1, 2] {
+                for bits_per_sample in [16, 24] {
+                    round_trip(sample_rate, channels, bits_per_sample, 10_000);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn round_trips_block_size_edges() {
+        let edges = [BLOCK_SIZE - 1, BLOCK_SIZE, BLOCK_SIZE + 1, 3 * BLOCK_SIZE];
+        for frames in [1, 2, 5].into_iter().chain(edges) {
+            round_trip(16000, 1, 16, frames);
+            round_trip(48000, 2, 24, frames);
+        }
+    }
+
+    #[test]
+    fn round_trips_many_channels() {
+        for channels in [3, 6, 8] {
+            round_trip(48000, channels, 16, BLOCK_SIZE + 100);
+        }
+    }
+
+    #[test]
+    fn round_trips_multi_byte_frame_numbers() {
+        // Frame numbers from 128 on take more than one byte
+        round_trip(8000, 1, 16, 130 * BLOCK_SIZE + 3);
+    }
+
+    #[test]
+    fn empty_file_is_valid() {
+        round_trip(16000, 1, 16, 0);
+    }
+
+    #[test]
+    fn rejects_unsupported_channel_counts() {
+        let dir = tempfile::tempdir().unwrap();
+        assert!(FlacWriter::new(dir.path().join("none.flac"), 16000, 0, 16).is_err());
+        assert!(FlacWriter::new(dir.path().join("nine.flac"), 16000, 9, 16).is_err());
+    }
+}
@@ -0,0 +1,130 @@
+use serde::Serialize;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Arc;
+use tauri::{AppHandle, Emitter};
+
+/// Length of each metering window
+const WINDOW_MS: u32 = 50;
+
+/// Floor used when converting silence to decibels
+const MIN_DB: f32 = -100.0;
+
+/// Input level for one metering window - returned to frontend
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InputLevel {
+    pub rms: f32,
+    pub peak: f32,
+    pub rms_db: f32,
+    pub peak_db: f32,
+    pub is_recording: bool,
+}
+
+impl InputLevel {
+    fn new(rms: f32, peak: f32, is_recording: bool) -> Self {
+        Self {
+            rms,
+            peak,
+            rms_db: to_db(rms),
+            peak_db: to_db(peak),
+            is_recording,
+        }
+    }
+}
+
+/// Payload for the `recorder-input-level` event
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InputLevelEvent {
+    pub recording_id: String,
+    #[serde(flatten)]
+    pub level: InputLevel,
+}
+
+/// Latest level, stored by the writer thread and read by `get_input_level`.
+/// Kept as f32 bits so polling never contends with the writer for a lock.
+#[derive(Default)]
+pub struct SharedLevel {
+    rms: AtomicU32,
+    peak: AtomicU32,
+}
+
+impl SharedLevel {
+    fn store(&self, rms: f32, peak: f32) {
+        self.rms.store(rms.to_bits(), Ordering::Relaxed);
+        self.peak.store(peak.to_bits(), Ordering::Relaxed);
+    }
+
+    pub fn load(&self, is_recording: bool) -> InputLevel {
+        InputLevel::new(
+            f32::from_bits(self.rms.load(Ordering::Relaxed)),
+            f32::from_bits(self.peak.load(Ordering::Relaxed)),
+            is_recording,
+        )
+    }
+}
+
+/// Computes RMS and peak over ~50ms windows and emits `recorder-input-level` events
+pub struct LevelMeter {
+    window_len: usize,
+    sum_squares: f64,
+    peak: f32,
+    count: usize,
+    shared: Arc<SharedLevel>,
+    recording_id: String,
+    app_handle: AppHandle,
+}
+
+impl LevelMeter {
+    pub fn new(
+        sample_rate: u32,
+        channels: u16,
+        shared: Arc<SharedLevel>,
+        recording_id: String,
+        app_handle: AppHandle,
+    ) -> Self {
+        let window_len = (sample_rate as usize * channels as usize * WINDOW_MS as usize) / 1000;
+        Self {
+            window_len: window_len.max(1),
+            sum_squares: 0.0,
+            peak: 0.0,
+            count: 0,
+            shared,
+            recording_id,
+            app_handle,
+        }
+    }
+
+    /// Feed interleaved samples; emits an event each time a window completes
+    pub fn process_samples(&mut self, data: &[f32], is_recording: bool) {
+        for &sample in data {
+            self.sum_squares += (sample as f64) * (sample as f64);
+            self.peak = self.peak.max(sample.abs());
+            self.count += 1;
+
+            if self.count == self.window_len {
+                let rms = (self.sum_squares / self.count as f64).sqrt() as f32;
+                self.shared.store(rms, self.peak);
+                let _ = self.app_handle.emit(
+                    "recorder-input-level",
+                    InputLevelEvent {
+                        recording_id: self.recording_id.clone(),
+                        level: InputLevel::new(rms, self.peak, is_recording),
+                    },
+                );
+
+                self.sum_squares = 0.0;
+                self.peak = 0.0;
+                self.count = 0;
+            }
+        }
+    }
+}
+
+fn to_db(value: f32) -> f32 {
+    if value > 0.0 {
+        (20.0 * value.log10()).max(MIN_DB)
+    } else {
+        MIN_DB
+    }
+}
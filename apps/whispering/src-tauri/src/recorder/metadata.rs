@@ -0,0 +1,221 @@
+use crate::recorder::auto_stop::AutoStopReason;
+use crate::recorder::dsp::RAW_FOLDER;
+use crate::recorder::encoder::RecordingFormat;
+use crate::recorder::error::RecorderError;
+use crate::recorder::quality::RecordingQuality;
+use crate::recorder::recorder::{AudioRecording, Result, SessionOptions};
+use crate::recorder::rotation::SEGMENT_FOLDER;
+use log::debug;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Layout version of the sidecar, bumped when a field changes meaning
+const METADATA_VERSION: u32 = 2;
+
+/// Subfolder of the recordings folder holding the sidecars. Kept out of the top
+/// level, where the app database takes any `{recording_id}.*` file for the audio.
+pub const METADATA_FOLDER: &str = "metadata";
+
+/// Self-describing record of a recording, kept as `metadata/{recording_id}.json`
+/// beside the audio file so it survives the app database being lost or the files moving
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RecordingMetadata {
+    pub version: u32,
+    pub recording_id: String,
+    pub app_version: String,
+    /// File names rather than paths, so the folder can be moved
+    pub file_name: String,
+    /// Relative to the folder of `file_name` (`raw/{recording_id}.{ext}`) since version 2
+    pub raw_file_name: Option<String>,
+    /// Every file of the recording in order, starting with `file_name`. Later
+    /// ones are relative to its folder (`segments/...`) since version 2.
+    #[serde(default)]
+    pub segment_file_names: Vec<String>,
+    pub format: RecordingFormat,
+    /// What was written to the file
+    pub sample_rate: u32,
+    pub channels: u16,
+    /// What the device delivered
+    pub capture_sample_rate: u32,
+    pub capture_channels: u16,
+    pub device_id: String,
+    pub device_name: Option<String>,
+    /// Wall-clock start and stop, in milliseconds since the Unix epoch
+    pub started_at_ms: Option<u64>,
+    pub stopped_at_ms: Option<u64>,
+    pub duration_seconds: f32,
+    pub paused_seconds: f32,
+    pub overrun_count: u64,
+    pub dropped_samples: u64,
+    /// Overruns of the second source, counted apart from the main input's
+    #[serde(default)]
+    pub secondary_overrun_count: Option<u64>,
+    #[serde(default)]
+    pub secondary_dropped_samples: Option<u64>,
+    /// Set when a session limit ended the recording
+    pub auto_stop_reason: Option<AutoStopReason>,
+    /// Set when a disk write failure ended the recording
+    #[serde(default)]
+    pub write_error: Option<String>,
+    /// Moments flagged with `add_recording_marker`, in the order they were added
+    #[serde(default)]
+    pub markers: Vec<RecordingMarker>,
+    /// Levels, clipping and silence measured while writing, set when recording stops
+    #[serde(default)]
+    pub quality: Option<RecordingQuality>,
+    pub options: SessionOptions,
+}
+
+impl RecordingMetadata {
+    /// Metadata for a new session; the caller fills in the device and formats
+    pub fn new(recording_id: String, file_path: &Path, options: SessionOptions) -> Self {
+        Self {
+            version: METADATA_VERSION,
+            recording_id,
+            app_version: env!("CARGO_PKG_VERSION").to_string(),
+            file_name: file_name(file_path),
+            raw_file_name: None,
+            segment_file_names: Vec::new(),
+            format: RecordingFormat::default(),
+            sample_rate: 0,
+            channels: 0,
+            capture_sample_rate: 0,
+            capture_channels: 0,
+            device_id: String::new(),
+            device_name: None,
+            started_at_ms: None,
+            stopped_at_ms: None,
+            duration_seconds: 0.0,
+            paused_seconds: 0.0,
+            overrun_count: 0,
+            dropped_samples: 0,
+            secondary_overrun_count: None,
+            secondary_dropped_samples: None,
+            auto_stop_reason: None,
+            write_error: None,
+            markers: Vec::new(),
+            quality: None,
+            options,
+        }
+    }
+
+    /// A recording (re)started in this session
+    pub fn start(&mut self) {
+        self.started_at_ms = Some(now_ms());
+        self.stopped_at_ms = None;
+        self.duration_seconds = 0.0;
+        self.paused_seconds = 0.0;
+        self.auto_stop_reason = None;
+        self.write_error = None;
+        self.quality = None;
+    }
+
+    /// Fill in what is only known once the file is finalized. Stopping again
+    /// after an auto-stop keeps the original stop time and reason.
+    pub fn finish(&mut self, recording: &AudioRecording, reason: Option<AutoStopReason>) {
+        self.stopped_at_ms.get_or_insert_with(now_ms);
+        self.auto_stop_reason = self.auto_stop_reason.or(reason);
+        self.raw_file_name = recording
+            .raw_file_path
+            .as_deref()
+            .map(|path| format!("{}/{}", RAW_FOLDER, file_name(Path::new(path))));
+        self.segment_file_names = recording
+            .segments
+            .iter()
+            .map(|segment| {
+                let name = file_name(Path::new(&segment.file_path));
+                match segment.index {
+                    1 => name,
+                    _ => format!("{}/{}", SEGMENT_FOLDER, name),
+                }
+            })
+            .collect();
+        self.duration_seconds = recording.duration_seconds;
+        self.paused_seconds = recording.paused_seconds;
+        self.overrun_count = recording.overrun_count;
+        self.dropped_samples = recording.dropped_samples;
+        self.secondary_overrun_count = recording.secondary_overrun_count;
+        self.secondary_dropped_samples = recording.secondary_dropped_samples;
+    }
+
+    /// Write the sidecar for `audio_path`, replacing any previous one whole
+    pub fn write(&self, audio_path: &Path) -> Result<()> {
+        let path = sidecar_path(audio_path);
+        if let Some(folder) = path.parent() {
+            std::fs::create_dir_all(folder).map_err(|e| {
+                RecorderError::disk_write(e, format!("Failed to create {:?}", folder))
+            })?;
+        }
+        let json = serde_json::to_vec_pretty(self).map_err(|e| RecorderError::Internal {
+            message: format!("Failed to serialize recording metadata: {}", e),
+        })?;
+
+        // Write then rename, so a crash never leaves a half-written sidecar
+        let temp_path = path.with_extension("json.tmp");
+        std::fs::write(&temp_path, json)
+            .and_then(|_| std::fs::rename(&temp_path, &path))
+            .map_err(|e| {
+                RecorderError::disk_write(e, format!("Failed to write metadata {:?}", path))
+            })?;
+        debug!("Wrote recording metadata {:?}", path);
+        Ok(())
+    }
+}
+
+/// A flagged moment in a recording - returned to frontend
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RecordingMarker {
+    /// 1-based, matching the cue point ID in WAV files
+    pub id: u32,
+    pub label: String,
+    /// Position in sample frames of the written file
+    pub frame: u64,
+    pub seconds: f32,
+}
+
+/// Sidecar location for an audio file: same name with a `.json` extension, in
+/// the metadata folder beside it
+pub fn sidecar_path(audio_path: &Path) -> PathBuf {
+    let file_name = audio_path.with_extension("json");
+    let file_name = file_name.file_name().unwrap_or_default();
+    audio_path
+        .parent()
+        .unwrap_or_else(|| Path::new(""))
+        .join(METADATA_FOLDER)
+        .join(file_name)
+}
+
+/// Read the metadata for a recording, given either its audio file or the sidecar itself
+pub fn read_metadata(path: &Path) -> Result<RecordingMetadata> {
+    let is_sidecar = path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("json"));
+    let path = if is_sidecar {
+        path.to_path_buf()
+    } else {
+        sidecar_path(path)
+    };
+
+    let json = std::fs::read(&path).map_err(|e| RecorderError::FileRead {
+        message: format!("Failed to read metadata {:?}: {}", path, e),
+    })?;
+    serde_json::from_slice(&json).map_err(|e| RecorderError::FileRead {
+        message: format!("Invalid metadata {:?}: {}", path, e),
+    })
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default()
+}
+
+fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_millis() as u64)
+}
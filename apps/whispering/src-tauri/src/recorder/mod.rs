@@ -1,12 +1,34 @@
+pub mod auto_stop;
 pub mod commands;
+pub mod device;
+pub mod disk_space;
+pub mod dsp;
+pub mod encoder;
+pub mod error;
+pub mod flac_writer;
+pub mod level_meter;
+pub mod metadata;
+pub mod opus_writer;
+pub mod output_converter;
+pub mod pre_roll;
+pub mod quality;
 pub mod recorder;
+pub mod recovery;
+pub mod rotation;
+pub mod source_mixer;
+pub mod status;
+pub mod streaming;
+pub mod vad;
 pub mod wav_writer;
 
 // Export everything from commands for easy access
 pub use commands::{
-    cancel_recording, close_recording_session, enumerate_recording_devices,
-    get_current_recording_id, init_recording_session, start_recording, stop_recording, AppData,
+    add_recording_marker, cancel_recording, close_recording_session, enumerate_recording_devices,
+    get_active_recording_ids, get_input_level, get_recorder_status, init_recording_session,
+    list_recoverable_recordings, pause_recording, read_recording_metadata, recover_recording,
+    resume_recording, start_recording, stop_recording, AppData,
 };
 
 // Export key types from recorder
+pub use device::RecordingDevice;
 pub use recorder::AudioRecording;
@@ -0,0 +1,308 @@
+use crate::recorder::output_converter::OutputConverter;
+use audiopus::coder::Encoder;
+use audiopus::{Application, Bitrate, Channels, SampleRate};
+use log::{debug, info};
+use ogg::writing::{PacketWriteEndInfo, PacketWriter};
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::PathBuf;
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+
+/// Ogg Opus granule positions are always counted at 48kHz
+const GRANULE_RATE: u32 = 48000;
+
+/// 20ms packets, the usual choice for speech
+const FRAME_MS: u32 = 20;
+
+/// Target bitrate per channel - transparent for speech
+const BITRATE_PER_CHANNEL: i32 = 32000;
+
+/// Largest packet the encoder may produce (recommended by libopus)
+const MAX_PACKET_SIZE: usize = 4000;
+
+/// Force a page boundary every second so data reaches the disk progressively
+const PACKETS_PER_PAGE: u64 = (1000 / FRAME_MS) as u64;
+
+/// Encoded bytes per second for a session with `channels`, ignoring Ogg framing
+pub fn bytes_per_second(channels: u16) -> u64 {
+    let encoder_channels = if channels == 2 { 2 } else { 1 };
+    (BITRATE_PER_CHANNEL as u64 * encoder_channels) / 8
+}
+
+/// Opus-in-Ogg writer with the same progressive-write lifecycle as `WavWriter`.
+///
+/// Opus only accepts 8/12/16/24/48kHz and mono or stereo, so other rates are
+/// resampled to 48kHz and more than two channels are mixed down to mono.
+pub struct OggOpusWriter {
+    packets: PacketWriter<BufWriter<File>>,
+    encoder: Encoder,
+    /// Resamples and downmixes to what the encoder accepts, compensating the resampler delay
+    converter: Option<OutputConverter>,
+    serial: u32,
+    input_sample_rate: u32,
+    input_channels: u16,
+    encoder_rate: u32,
+    encoder_channels: usize,
+    pre_skip: u64,
+    /// Interleaved encoder-rate samples waiting for a full frame
+    pending: Vec<f32>,
+    packet_buffer: Vec<u8>,
+    packets_written: u64,
+    /// Frames (per channel) handed to the encoder, at the encoder rate
+    frames_encoded: u64,
+    samples_written: u64,
+    finalized: bool,
+    last_flush: Instant,
+    file_path: PathBuf,
+}
+
+impl OggOpusWriter {
+    /// Create a new Ogg Opus file and write the identification and comment headers
+    pub fn new(file_path: PathBuf, sample_rate: u32, channels: u16) -> io::Result<Self> {
+        let (encoder_rate, opus_rate) = match opus_sample_rate(sample_rate) {
+            Some(rate) => (sample_rate, rate),
+            None => (GRANULE_RATE, SampleRate::Hz48000),
+        };
+        let (encoder_channels, opus_channels) = if channels == 2 {
+            (2, Channels::Stereo)
+        } else {
+            (1, Channels::Mono)
+        };
+
+        let mut encoder = Encoder::new(opus_rate, opus_channels, Application::Voip)
+            .map_err(|e| io::Error::other(format!("Failed to create Opus encoder: {}", e)))?;
+        encoder
+            .set_bitrate(Bitrate::BitsPerSecond(
+                BITRATE_PER_CHANNEL * encoder_channels as i32,
+            ))
+            .map_err(|e| io::Error::other(format!("Failed to set Opus bitrate: {}", e)))?;
+        let lookahead = encoder
+            .lookahead()
+            .map_err(|e| io::Error::other(format!("Failed to query Opus lookahead: {}", e)))?;
+        // Pre-skip is expressed at 48kHz regardless of the encoder rate
+        let pre_skip = lookahead as u64 * (GRANULE_RATE / encoder_rate) as u64;
+
+        let converter = if encoder_rate != sample_rate || encoder_channels != channels as usize {
+            Some(
+                OutputConverter::new(sample_rate, channels, encoder_rate, encoder_channels as u16)
+                    .map_err(|e| io::Error::other(e.to_string()))?,
+            )
+        } else {
+            None
+        };
+
+        let file = File::create(&file_path)?;
+        let mut packets = PacketWriter::new(BufWriter::new(file));
+        let serial = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.subsec_nanos())
+            .unwrap_or(0x5753_5052);
+
+        // Identification header (RFC 7845 section 5.1), alone on the first page
+        let mut head = Vec::with_capacity(19);
+        head.extend_from_slice(b"OpusHead");
+        head.push(1); // Version
+        head.push(encoder_channels as u8);
+        head.extend_from_slice(&(pre_skip as u16).to_le_bytes());
+        head.extend_from_slice(&sample_rate.to_le_bytes()); // Original input rate
+        head.extend_from_slice(&0i16.to_le_bytes()); // Output gain
+        head.push(0); // Channel mapping family 0 (mono/stereo)
+        packets.write_packet(head.into_boxed_slice(), serial, PacketWriteEndInfo::EndPage, 0)?;
+
+        // Comment header (section 5.2)
+        let vendor = concat!("whispering ", env!("CARGO_PKG_VERSION"));
+        let mut tags = Vec::new();
+        tags.extend_from_slice(b"OpusTags");
+        tags.extend_from_slice(&(vendor.len() as u32).to_le_bytes());
+        tags.extend_from_slice(vendor.as_bytes());
+        tags.extend_from_slice(&0u32.to_le_bytes()); // No user comments
+        packets.write_packet(tags.into_boxed_slice(), serial, PacketWriteEndInfo::EndPage, 0)?;
+        packets.inner_mut().flush()?;
+
+        info!(
+            "Created Ogg Opus file at {:?}: {}Hz input, {}Hz encoder, {} channels",
+            file_path, sample_rate, encoder_rate, encoder_channels
+        );
+
+        Ok(Self {
+            packets,
+            encoder,
+            converter,
+            serial,
+            input_sample_rate: sample_rate,
+            input_channels: channels,
+            encoder_rate,
+            encoder_channels,
+            pre_skip,
+            pending: Vec::new(),
+            packet_buffer: vec![0; MAX_PACKET_SIZE],
+            packets_written: 0,
+            frames_encoded: 0,
+            samples_written: 0,
+            finalized: false,
+            last_flush: Instant::now(),
+            file_path,
+        })
+    }
+
+    /// Write interleaved f32 samples
+    pub fn write_samples_f32(&mut self, samples: &[f32]) -> io::Result<()> {
+        if self.finalized {
+            return Err(io::Error::other("Opus writer already finalized"));
+        }
+
+        match self.converter.as_mut() {
+            Some(converter) => {
+                let converted = converter
+                    .process(samples)
+                    .map_err(|e| io::Error::other(e.to_string()))?;
+                self.pending.extend_from_slice(converted);
+            }
+            None => self.pending.extend_from_slice(samples),
+        }
+        self.encode_full_frames()?;
+
+        self.samples_written += samples.len() as u64;
+
+        // Flush periodically (every second)
+        if self.last_flush.elapsed().as_secs() >= 1 {
+            self.packets.inner_mut().flush()?;
+            self.last_flush = Instant::now();
+        }
+
+        Ok(())
+    }
+
+    fn frame_len(&self) -> usize {
+        (self.encoder_rate * FRAME_MS / 1000) as usize * self.encoder_channels
+    }
+
+    fn encode_full_frames(&mut self) -> io::Result<()> {
+        let frame_len = self.frame_len();
+        while self.pending.len() >= frame_len {
+            let frame: Vec<f32> = self.pending.drain(..frame_len).collect();
+            self.encode_frame(&frame, false)?;
+        }
+        Ok(())
+    }
+
+    fn encode_frame(&mut self, frame: &[f32], last: bool) -> io::Result<()> {
+        let len = self
+            .encoder
+            .encode_float(frame, &mut self.packet_buffer)
+            .map_err(|e| io::Error::other(format!("Opus encoding failed: {}", e)))?;
+
+        self.frames_encoded += (frame.len() / self.encoder_channels) as u64;
+        self.packets_written += 1;
+
+        let (end_info, granule) = if last {
+            // The final granule position trims the zero padding of the last frame
+            let input_frames = self.samples_written / self.input_channels.max(1) as u64;
+            let real_frames = input_frames * GRANULE_RATE as u64 / self.input_sample_rate as u64;
+            (
+                PacketWriteEndInfo::EndStream,
+                self.pre_skip + real_frames.min(self.granule_frames()),
+            )
+        } else if self.packets_written.is_multiple_of(PACKETS_PER_PAGE) {
+            (PacketWriteEndInfo::EndPage, self.pre_skip + self.granule_frames())
+        } else {
+            (PacketWriteEndInfo::NormalPacket, self.pre_skip + self.granule_frames())
+        };
+
+        self.packets.write_packet(
+            self.packet_buffer[..len].to_vec().into_boxed_slice(),
+            self.serial,
+            end_info,
+            granule,
+        )
+    }
+
+    /// Encoded frames expressed at 48kHz
+    fn granule_frames(&self) -> u64 {
+        self.frames_encoded * (GRANULE_RATE / self.encoder_rate) as u64
+    }
+
+    /// Finalize the Ogg stream: flush the converter, pad the last packet and end the stream
+    pub fn finalize(&mut self) -> io::Result<()> {
+        if !self.finalized {
+            if let Some(converter) = self.converter.as_mut() {
+                let tail = converter
+                    .flush()
+                    .map_err(|e| io::Error::other(e.to_string()))?;
+                self.pending.extend_from_slice(tail);
+            }
+
+            // Always end with an EndStream packet, padded with silence
+            let frame_len = self.frame_len();
+            let mut last: Vec<f32> = self.pending.drain(..).collect();
+            last.resize(frame_len, 0.0);
+            self.encode_frame(&last, true)?;
+            self.finalized = true;
+        }
+        self.packets.inner_mut().flush()?;
+
+        info!(
+            "Finalized Ogg Opus file {:?}: {} samples, {:.2} seconds",
+            self.file_path,
+            self.samples_written,
+            self.get_duration_seconds()
+        );
+        debug!(
+            "Opus stream: {} packets, pre-skip {}",
+            self.packets_written, self.pre_skip
+        );
+
+        Ok(())
+    }
+
+    /// Get the current duration in seconds
+    pub fn get_duration_seconds(&self) -> f32 {
+        self.samples_written as f32
+            / (self.input_sample_rate as f32 * self.input_channels as f32)
+    }
+
+    /// Get the file path
+    pub fn get_file_path(&self) -> &PathBuf {
+        &self.file_path
+    }
+
+    /// Get audio metadata. The channel count is what the file holds, which is
+    /// mono when more than two channels were mixed down.
+    pub fn get_metadata(&self) -> (u32, u16, f32) {
+        (
+            self.input_sample_rate,
+            self.encoder_channels as u16,
+            self.get_duration_seconds(),
+        )
+    }
+
+    /// Sample rate and channel count `write_samples_f32` expects
+    pub fn input_format(&self) -> (u32, u16) {
+        (self.input_sample_rate, self.input_channels)
+    }
+
+    /// Flush any buffered data to disk
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.packets.inner_mut().flush()
+    }
+}
+
+impl Drop for OggOpusWriter {
+    fn drop(&mut self) {
+        if let Err(e) = self.finalize() {
+            log::error!("Failed to finalize Ogg Opus file on drop: {}", e);
+        }
+    }
+}
+
+/// Opus encoder rate matching the device rate, if there is one
+fn opus_sample_rate(sample_rate: u32) -> Option<SampleRate> {
+    match sample_rate {
+        8000 => Some(SampleRate::Hz8000),
+        12000 => Some(SampleRate::Hz12000),
+        16000 => Some(SampleRate::Hz16000),
+        24000 => Some(SampleRate::Hz24000),
+        48000 => Some(SampleRate::Hz48000),
+        _ => None,
+    }
+}
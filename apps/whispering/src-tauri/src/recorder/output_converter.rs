@@ -0,0 +1,152 @@
+use crate::recorder::error::RecorderError;
+use crate::recorder::recorder::Result;
+use rubato::{FftFixedIn, Resampler};
+
+/// Frames per resampler call
+const RESAMPLER_CHUNK: usize = 1024;
+
+/// Downmixes and resamples captured audio on the writer thread, so the file can
+/// be written directly in the format transcription wants (e.g. 16kHz mono).
+pub struct OutputConverter {
+    input_channels: usize,
+    output_channels: usize,
+    input_rate: u32,
+    output_rate: u32,
+    resampler: Option<FftFixedIn<f32>>,
+    /// Resampler output frames still to discard, so the file isn't shifted by its delay
+    delay_frames: usize,
+    /// Frames fed to and emitted from the resampler, for trimming the tail on flush
+    frames_in: u64,
+    frames_out: u64,
+    /// Per-channel input waiting for the resampler
+    resampler_input: Vec<Vec<f32>>,
+    /// Interleaved converted samples handed back to the caller
+    output: Vec<f32>,
+}
+
+impl OutputConverter {
+    pub fn new(
+        input_rate: u32,
+        input_channels: u16,
+        output_rate: u32,
+        output_channels: u16,
+    ) -> Result<Self> {
+        let output_channels = output_channels.max(1) as usize;
+        let resampler = if input_rate != output_rate {
+            Some(
+                FftFixedIn::<f32>::new(
+                    input_rate as usize,
+                    output_rate as usize,
+                    RESAMPLER_CHUNK,
+                    2,
+                    output_channels,
+                )
+                .map_err(|e| RecorderError::Processing {
+                    message: format!("Failed to create resampler: {}", e),
+                })?,
+            )
+        } else {
+            None
+        };
+
+        let delay_frames = resampler.as_ref().map_or(0, |r| r.output_delay());
+
+        Ok(Self {
+            input_channels: input_channels.max(1) as usize,
+            output_channels,
+            input_rate,
+            output_rate,
+            resampler,
+            delay_frames,
+            frames_in: 0,
+            frames_out: 0,
+            resampler_input: vec![Vec::with_capacity(RESAMPLER_CHUNK); output_channels],
+            output: Vec::new(),
+        })
+    }
+
+    /// Convert interleaved input samples. The result may lag the input by up to
+    /// one resampler chunk; `flush` returns whatever is still buffered.
+    pub fn process(&mut self, data: &[f32]) -> Result<&[f32]> {
+        self.output.clear();
+
+        for frame in data.chunks_exact(self.input_channels) {
+            for ch in 0..self.output_channels {
+                let sample = if self.output_channels == 1 {
+                    frame.iter().sum::<f32>() / self.input_channels as f32
+                } else {
+                    frame[ch % self.input_channels]
+                };
+
+                if self.resampler.is_some() {
+                    self.resampler_input[ch].push(sample);
+                } else {
+                    self.output.push(sample);
+                }
+            }
+
+            if self.resampler_input[0].len() == RESAMPLER_CHUNK {
+                self.run_resampler(false)?;
+            }
+        }
+
+        Ok(&self.output)
+    }
+
+    /// Push the buffered tail through the resampler, then drain its delay line
+    /// until the output length matches the input duration
+    pub fn flush(&mut self) -> Result<&[f32]> {
+        self.output.clear();
+        if self.resampler.is_none() {
+            return Ok(&self.output);
+        }
+
+        let total_in = self.frames_in + self.resampler_input[0].len() as u64;
+        let expected_frames = total_in * self.output_rate as u64 / self.input_rate as u64;
+        // Each partial call yields up to a chunk, so a few calls always cover the delay
+        for _ in 0..4 {
+            if self.frames_out >= expected_frames {
+                break;
+            }
+            self.run_resampler(true)?;
+        }
+
+        let excess = self.frames_out.saturating_sub(expected_frames) as usize;
+        let keep = (self.output.len() / self.output_channels).saturating_sub(excess);
+        self.output.truncate(keep * self.output_channels);
+        self.frames_out -= excess as u64;
+        Ok(&self.output)
+    }
+
+    fn run_resampler(&mut self, partial: bool) -> Result<()> {
+        let Some(resampler) = self.resampler.as_mut() else {
+            return Ok(());
+        };
+
+        self.frames_in += self.resampler_input[0].len() as u64;
+        let output = if partial {
+            // rubato treats an empty channel as inactive, so pass nothing to drain the delay line
+            let input = (!self.resampler_input[0].is_empty()).then_some(&self.resampler_input[..]);
+            resampler.process_partial(input, None)
+        } else {
+            resampler.process(&self.resampler_input, None)
+        }
+        .map_err(|e| RecorderError::Processing {
+            message: format!("Resampling failed: {}", e),
+        })?;
+
+        for buffer in &mut self.resampler_input {
+            buffer.clear();
+        }
+
+        let skip = self.delay_frames.min(output[0].len());
+        self.delay_frames -= skip;
+        for i in skip..output[0].len() {
+            for channel in &output {
+                self.output.push(channel[i]);
+            }
+        }
+        self.frames_out += (output[0].len() - skip) as u64;
+        Ok(())
+    }
+}
@@ -0,0 +1,51 @@
+use std::collections::VecDeque;
+
+/// Upper bound on the configurable pre-roll
+pub const MAX_PRE_ROLL_MS: u32 = 2000;
+
+/// Ring buffer holding the most recent audio captured while not recording.
+/// Its contents are written ahead of the first samples when recording starts,
+/// so speech that begins before `start_recording` round-trips isn't clipped.
+pub struct PreRollBuffer {
+    samples: VecDeque<f32>,
+    capacity: usize,
+}
+
+impl PreRollBuffer {
+    pub fn new(pre_roll_ms: u32, sample_rate: u32, channels: u16) -> Self {
+        let pre_roll_ms = pre_roll_ms.min(MAX_PRE_ROLL_MS);
+        let frames = (sample_rate as u64 * pre_roll_ms as u64 / 1000) as usize;
+        // Whole frames only, so the buffer never splits an interleaved frame
+        let capacity = frames * channels as usize;
+        Self {
+            samples: VecDeque::with_capacity(capacity),
+            capacity,
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.capacity > 0
+    }
+
+    /// Append interleaved samples, dropping the oldest once full
+    pub fn push(&mut self, data: &[f32]) {
+        if !self.is_enabled() {
+            return;
+        }
+
+        // Only the tail of an oversized buffer can survive
+        let data = &data[data.len().saturating_sub(self.capacity)..];
+        let overflow = (self.samples.len() + data.len()).saturating_sub(self.capacity);
+        self.samples.drain(..overflow);
+        self.samples.extend(data.iter().copied());
+    }
+
+    /// Buffered audio in chronological order
+    pub fn as_slices(&self) -> (&[f32], &[f32]) {
+        self.samples.as_slices()
+    }
+
+    pub fn clear(&mut self) {
+        self.samples.clear();
+    }
+}
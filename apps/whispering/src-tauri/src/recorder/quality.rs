@@ -0,0 +1,413 @@
+use serde::{Deserialize, Serialize};
+use std::f64::consts::PI;
+
+/// Samples at or above this magnitude count as clipped
+const CLIP_LEVEL: f32 = 0.999;
+
+/// Length of each window the silence ratio is measured over
+const SILENCE_WINDOW_MS: u32 = 50;
+
+/// RMS level in dBFS below which a window counts as silence
+const SILENCE_THRESHOLD_DB: f32 = -50.0;
+
+/// Loudness is measured in 400ms blocks overlapping by 75%, built from 100ms steps
+const LOUDNESS_STEP_MS: u32 = 100;
+const STEPS_PER_BLOCK: usize = 4;
+
+/// BS.1770 gates: blocks below -70 LUFS, then blocks 10 LU below the
+/// loudness of what's left, don't count toward the integrated loudness
+const ABSOLUTE_GATE_LUFS: f64 = -70.0;
+const RELATIVE_GATE_LU: f64 = -10.0;
+
+/// Floor used when converting silence to decibels
+const MIN_DB: f32 = -100.0;
+
+/// How good the written audio is - returned to frontend and kept in the sidecar
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RecordingQuality {
+    /// Largest sample magnitude, linear and in dBFS
+    pub peak: f32,
+    pub peak_db: f32,
+    /// RMS over the whole recording, all channels, in dBFS
+    pub rms_db: f32,
+    /// Integrated loudness (ITU-R BS.1770, gated). `None` for recordings shorter
+    /// than 400ms or with nothing above the -70 LUFS gate, i.e. effectively silent.
+    pub loudness_lufs: Option<f32>,
+    pub clipped_samples: u64,
+    /// Largest per-channel mean sample value; far from zero hints at a faulty mic or interface
+    pub dc_offset: f32,
+    /// Share of the recording (0-1) quieter than -50 dBFS
+    pub silence_ratio: f32,
+}
+
+/// One second-order section of the K-weighting filter (transposed direct form II)
+#[derive(Clone)]
+struct Section {
+    b0: f64,
+    b1: f64,
+    b2: f64,
+    a1: f64,
+    a2: f64,
+    z1: f64,
+    z2: f64,
+}
+
+impl Section {
+    fn process(&mut self, x: f64) -> f64 {
+        let y = self.b0 * x + self.z1;
+        self.z1 = self.b1 * x - self.a1 * y + self.z2;
+        self.z2 = self.b2 * x - self.a2 * y;
+        y
+    }
+}
+
+/// BS.1770 K-weighting: a high shelf modelling the head, then a high-pass.
+/// Coefficients are derived for any sample rate rather than the 48kHz tables.
+#[derive(Clone)]
+struct KWeighting {
+    shelf: Section,
+    high_pass: Section,
+}
+
+impl KWeighting {
+    fn new(sample_rate: u32) -> Self {
+        let rate = sample_rate.max(1) as f64;
+
+        let k = (PI * 1681.974450955533 / rate).tan();
+        let q = 0.7071752369554196;
+        let vh = 10f64.powf(3.999843853973347 / 20.0);
+        let vb = vh.powf(0.4996667741545416);
+        let a0 = 1.0 + k / q + k * k;
+        let shelf = Section {
+            b0: (vh + vb * k / q + k * k) / a0,
+            b1: 2.0 * (k * k - vh) / a0,
+            b2: (vh - vb * k / q + k * k) / a0,
+            a1: 2.0 * (k * k - 1.0) / a0,
+            a2: (1.0 - k / q + k * k) / a0,
+            z1: 0.0,
+            z2: 0.0,
+        };
+
+        let k = (PI * 38.13547087602444 / rate).tan();
+        let q = 0.5003270373238773;
+        let a0 = 1.0 + k / q + k * k;
+        let high_pass = Section {
+            b0: 1.0,
+            b1: -2.0,
+            b2: 1.0,
+            a1: 2.0 * (k * k - 1.0) / a0,
+            a2: (1.0 - k / q + k * k) / a0,
+            z1: 0.0,
+            z2: 0.0,
+        };
+
+        Self { shelf, high_pass }
+    }
+
+    fn process(&mut self, x: f32) -> f64 {
+        self.high_pass.process(self.shelf.process(x as f64))
+    }
+}
+
+/// Accumulates quality statistics over the audio written to a recording.
+/// Everything is incremental; only one loudness value per 100ms is kept.
+pub struct QualityMeter {
+    sample_rate: u32,
+    channels: usize,
+    peak: f32,
+    sum_squares: f64,
+    clipped_samples: u64,
+    frames: u64,
+    /// Per channel, for the DC offset
+    channel_sums: Vec<f64>,
+    silence_threshold: f32,
+    window_len: usize,
+    window_sum_squares: f64,
+    window_count: usize,
+    windows: u64,
+    silent_windows: u64,
+    filters: Vec<KWeighting>,
+    step_len: usize,
+    step_sum: f64,
+    step_count: usize,
+    /// K-weighted mean square of each completed 100ms step, summed over channels
+    steps: Vec<f64>,
+}
+
+impl QualityMeter {
+    pub fn new(sample_rate: u32, channels: u16) -> Self {
+        let channels = channels.max(1) as usize;
+        Self {
+            sample_rate,
+            channels,
+            peak: 0.0,
+            sum_squares: 0.0,
+            clipped_samples: 0,
+            frames: 0,
+            channel_sums: vec![0.0; channels],
+            silence_threshold: 10f32.powf(SILENCE_THRESHOLD_DB / 20.0),
+            window_len: ((sample_rate * SILENCE_WINDOW_MS / 1000) as usize).max(1) * channels,
+            window_sum_squares: 0.0,
+            window_count: 0,
+            windows: 0,
+            silent_windows: 0,
+            filters: vec![KWeighting::new(sample_rate); channels],
+            step_len: ((sample_rate * LOUDNESS_STEP_MS / 1000) as usize).max(1),
+            step_sum: 0.0,
+            step_count: 0,
+            steps: Vec::new(),
+        }
+    }
+
+    /// Start over for a new recording
+    pub fn reset(&mut self) {
+        *self = Self::new(self.sample_rate, self.channels as u16);
+    }
+
+    /// Feed interleaved samples as they are written
+    pub fn process(&mut self, data: &[f32]) {
+        for frame in data.chunks_exact(self.channels) {
+            for (channel, &sample) in frame.iter().enumerate() {
+                let magnitude = sample.abs();
+                self.peak = self.peak.max(magnitude);
+                if magnitude >= CLIP_LEVEL {
+                    self.clipped_samples += 1;
+                }
+                let square = sample as f64 * sample as f64;
+                self.sum_squares += square;
+                self.channel_sums[channel] += sample as f64;
+                self.window_sum_squares += square;
+
+                let weighted = self.filters[channel].process(sample);
+                self.step_sum += weighted * weighted;
+            }
+            self.frames += 1;
+
+            self.window_count += self.channels;
+            if self.window_count >= self.window_len {
+                self.close_window();
+            }
+
+            self.step_count += 1;
+            if self.step_count == self.step_len {
+                self.steps.push(self.step_sum / self.step_len as f64);
+                self.step_sum = 0.0;
+                self.step_count = 0;
+            }
+        }
+    }
+
+    fn close_window(&mut self) {
+        let rms = (self.window_sum_squares / self.window_count as f64).sqrt() as f32;
+        self.windows += 1;
+        if rms < self.silence_threshold {
+            self.silent_windows += 1;
+        }
+        self.window_sum_squares = 0.0;
+        self.window_count = 0;
+    }
+
+    /// Statistics for everything written since the last reset
+    pub fn summary(&self) -> RecordingQuality {
+        let samples = self.frames * self.channels as u64;
+        let rms = if samples == 0 {
+            0.0
+        } else {
+            (self.sum_squares / samples as f64).sqrt() as f32
+        };
+        let dc_offset = if self.frames == 0 {
+            0.0
+        } else {
+            self.channel_sums
+                .iter()
+                .map(|sum| (sum / self.frames as f64).abs() as f32)
+                .fold(0.0, f32::max)
+        };
+
+        // A partial last window counts as a whole one
+        let mut windows = self.windows;
+        let mut silent_windows = self.silent_windows;
+        if self.window_count > 0 {
+            windows += 1;
+            let rms = (self.window_sum_squares / self.window_count as f64).sqrt() as f32;
+            if rms < self.silence_threshold {
+                silent_windows += 1;
+            }
+        }
+        let silence_ratio = if windows == 0 {
+            1.0
+        } else {
+            silent_windows as f32 / windows as f32
+        };
+
+        RecordingQuality {
+            peak: self.peak,
+            peak_db: to_db(self.peak),
+            rms_db: to_db(rms),
+            loudness_lufs: self.integrated_loudness().map(|lufs| lufs as f32),
+            clipped_samples: self.clipped_samples,
+            dc_offset,
+            silence_ratio,
+        }
+    }
+
+    /// Gated integrated loudness per BS.1770, from the 100ms steps
+    fn integrated_loudness(&self) -> Option<f64> {
+        let loudness = |mean_square: f64| -0.691 + 10.0 * mean_square.log10();
+        let mean = |blocks: &[f64]| blocks.iter().sum::<f64>() / blocks.len() as f64;
+
+        let blocks: Vec<f64> = self
+            .steps
+            .windows(STEPS_PER_BLOCK)
+            .map(|steps| steps.iter().sum::<f64>() / STEPS_PER_BLOCK as f64)
+            .filter(|&block| loudness(block) > ABSOLUTE_GATE_LUFS)
+            .collect();
+        if blocks.is_empty() {
+            return None;
+        }
+
+        let relative_gate = loudness(mean(&blocks)) + RELATIVE_GATE_LU;
+        let gated: Vec<f64> = blocks
+            .into_iter()
+            .filter(|&block| loudness(block) > relative_gate)
+            .collect();
+        Some(loudness(mean(&gated)))
+    }
+}
+
+fn to_db(value: f32) -> f32 {
+    if value > 0.0 {
+        (20.0 * value.log10()).max(MIN_DB)
+    } else {
+        MIN_DB
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Interleaved sine of `frequency` Hz at `amplitude`, identical on every channel
+    fn sine(
+        sample_rate: u32,
+        channels: u16,
+        frequency: f64,
+        amplitude: f64,
+        seconds: f64,
+    ) -> Vec<f32> {
+        let frames = (sample_rate as f64 * seconds) as usize;
+        (0..frames)
+            .flat_map(|i| {
+                let t = i as f64 / sample_rate as f64;
+                let sample = (amplitude * (2.0 * PI * frequency * t).sin()) as f32;
+                std::iter::repeat_n(sample, channels as usize)
+            })
+            .collect()
+    }
+
+    fn measure(sample_rate: u32, channels: u16, data: &[f32]) -> RecordingQuality {
+        let mut meter = QualityMeter::new(sample_rate, channels);
+        // Uneven pieces, as the writer thread hands them over
+        for piece in data.chunks(channels as usize * 997) {
+            meter.process(piece);
+        }
+        meter.summary()
+    }
+
+    fn assert_close(actual: f32, expected: f32, tolerance: f32) {
+        assert!(
+            (actual - expected).abs() <= tolerance,
+            "expected {} ± {}, got {}",
+            expected,
+            tolerance,
+            actual
+        );
+    }
+
+    #[test]
+    fn full_scale_sine_reads_minus_3_lufs() {
+        // BS.1770: a 0 dBFS 997Hz sine in one channel measures -3.01 LKFS
+        let quality = measure(48000, 1, &sine(48000, 1, 997.0, 1.0, 5.0));
+        assert_close(quality.loudness_lufs.unwrap(), -3.01, 0.05);
+        assert_close(quality.peak_db, 0.0, 0.01);
+        assert_close(quality.rms_db, -3.01, 0.01);
+    }
+
+    #[test]
+    fn loudness_follows_level_at_any_sample_rate() {
+        for sample_rate in [16000, 44100, 48000, 96000] {
+            let quality = measure(sample_rate, 1, &sine(sample_rate, 1, 1000.0, 0.1, 5.0));
+            assert_close(quality.loudness_lufs.unwrap(), -23.01, 0.1);
+            assert_close(quality.peak_db, -20.0, 0.01);
+        }
+    }
+
+    #[test]
+    fn channels_add_up() {
+        // The same signal in both channels is 3dB louder than in one
+        let quality = measure(48000, 2, &sine(48000, 2, 997.0, 0.1, 5.0));
+        assert_close(quality.loudness_lufs.unwrap(), -20.0, 0.05);
+        assert_close(quality.rms_db, -23.01, 0.01);
+    }
+
+    #[test]
+    fn quiet_passages_are_gated() {
+        let mut data = sine(48000, 1, 997.0, 0.1, 5.0);
+        data.extend(sine(48000, 1, 997.0, 0.001, 5.0));
+        let quality = measure(48000, 1, &data);
+        // -63 LUFS is above the absolute gate but 10 LU below the rest, so only
+        // the blocks straddling the change pull the result below -23.01 (ungated: -26)
+        assert_close(quality.loudness_lufs.unwrap(), -23.01, 0.2);
+        assert_close(quality.silence_ratio, 0.5, 0.01);
+    }
+
+    #[test]
+    fn digital_silence() {
+        let quality = measure(48000, 2, &vec![0.0; 48000 * 2 * 3]);
+        assert_eq!(quality.loudness_lufs, None);
+        assert_eq!(quality.peak_db, MIN_DB);
+        assert_eq!(quality.rms_db, MIN_DB);
+        assert_eq!(quality.clipped_samples, 0);
+        assert_eq!(quality.dc_offset, 0.0);
+        assert_eq!(quality.silence_ratio, 1.0);
+    }
+
+    #[test]
+    fn too_short_for_loudness() {
+        let quality = measure(48000, 1, &sine(48000, 1, 997.0, 1.0, 0.3));
+        assert_eq!(quality.loudness_lufs, None);
+        assert_eq!(quality.silence_ratio, 0.0);
+    }
+
+    #[test]
+    fn empty_recording() {
+        let quality = measure(48000, 1, &[]);
+        assert_eq!(quality.loudness_lufs, None);
+        assert_eq!(quality.peak, 0.0);
+        assert_eq!(quality.silence_ratio, 1.0);
+    }
+
+    #[test]
+    fn counts_clipping_and_dc_offset() {
+        // Left channel sits at +0.25, right channel clips on every other frame
+        let data: Vec<f32> = (0..4800)
+            .flat_map(|i| [0.25, if i % 2 == 0 { 1.0 } else { -0.5 }])
+            .collect();
+        let quality = measure(48000, 2, &data);
+        assert_eq!(quality.clipped_samples, 2400);
+        assert_eq!(quality.peak, 1.0);
+        assert_close(quality.dc_offset, 0.25, 1e-6);
+    }
+
+    #[test]
+    fn reset_starts_over() {
+        let mut meter = QualityMeter::new(48000, 1);
+        meter.process(&sine(48000, 1, 997.0, 1.0, 1.0));
+        meter.reset();
+        meter.process(&vec![0.0; 48000]);
+        let quality = meter.summary();
+        assert_eq!(quality.peak, 0.0);
+        assert_eq!(quality.loudness_lufs, None);
+    }
+}
@@ -1,15 +1,35 @@
-use crate::recorder::wav_writer::WavWriter;
-use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
-use cpal::{Device, SampleFormat, Stream};
-use serde::Serialize;
+use crate::recorder::auto_stop::{AutoStop, AutoStopReason, SilenceTimeout};
+use crate::recorder::device;
+use crate::recorder::disk_space::{DiskSpace, MIN_MINUTES_REMAINING};
+use crate::recorder::dsp::{DspChain, DspConfig, RAW_FOLDER};
+use crate::recorder::encoder::{RecordingFormat, RecordingWriter};
+use crate::recorder::error::RecorderError;
+use crate::recorder::level_meter::{InputLevel, LevelMeter, SharedLevel};
+use crate::recorder::metadata::{self, RecordingMarker, RecordingMetadata};
+use crate::recorder::output_converter::OutputConverter;
+use crate::recorder::pre_roll::PreRollBuffer;
+use crate::recorder::quality::{QualityMeter, RecordingQuality};
+use crate::recorder::rotation::{RecordingSegment, RotationConfig, Rotator};
+use crate::recorder::source_mixer::{SourceLayout, SourceMixer};
+use crate::recorder::status::{RecorderPhase, RecorderStatus, SessionStatus};
+use crate::recorder::streaming::{Chunker, StreamingConfig, StreamingHandle};
+use crate::recorder::vad::{VadConfig, VadStage};
+use crate::recorder::wav_writer::WavSampleFormat;
+use cpal::traits::{DeviceTrait, StreamTrait};
+use cpal::{Device, FromSample, SampleFormat, SizedSample, Stream, I24};
+use serde::{Deserialize, Serialize};
+use std::io;
 use std::path::PathBuf;
-use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use std::sync::{mpsc, Arc, Mutex};
 use std::thread::{self, JoinHandle};
-use log::{debug, error, info};
+use std::time::{Duration, Instant};
+use log::{debug, error, info, warn};
+use rtrb::{Consumer, Producer, RingBuffer};
+use tauri::{AppHandle, Emitter};
 
-/// Simple result type using String for errors
-pub type Result<T> = std::result::Result<T, String>;
+/// Result type for recorder operations, serialized to the frontend on error
+pub type Result<T> = std::result::Result<T, RecorderError>;
 
 /// Audio recording metadata - returned to frontend
 #[derive(Debug, Clone, Serialize)]
@@ -19,23 +39,185 @@ pub struct AudioRecording {
     pub sample_rate: u32,
     pub channels: u16,
     pub duration_seconds: f32,
-    pub file_path: Option<String>, // Path to the WAV file
+    pub paused_seconds: f32, // Wall-clock time spent paused, not included in duration
+    pub overrun_count: u64,  // Callbacks that found the ring buffer full
+    pub dropped_samples: u64, // Samples lost to those overruns
+    pub secondary_overrun_count: Option<u64>, // Same for the second source, when the session has one
+    pub secondary_dropped_samples: Option<u64>,
+    pub format: RecordingFormat,
+    pub file_extension: String,
+    pub file_path: Option<String>,     // Path to the recording file
+    pub raw_file_path: Option<String>, // Unprocessed copy, when the session kept one
+    pub transcript: Option<String>,    // Stitched streaming transcript, when the session streamed
+    pub markers: Vec<RecordingMarker>, // Moments flagged while recording
+    /// Levels, clipping and silence of the written audio; `None` for recovered files
+    pub quality: Option<RecordingQuality>,
+    /// Every file of the recording in order; more than one when the session rotates files
+    pub segments: Vec<RecordingSegment>,
 }
 
-/// Simple recorder commands for worker thread communication
-#[derive(Debug)]
+/// Ring buffer overruns, counted in the callback and reset when recording starts
+#[derive(Default)]
+struct OverrunCounters {
+    count: AtomicU64,
+    dropped_samples: AtomicU64,
+    /// The second source's stream counts into its own, so its drops aren't
+    /// reported as the main input's
+    secondary: Option<Arc<OverrunCounters>>,
+}
+
+impl OverrunCounters {
+    fn reset(&self) {
+        self.count.store(0, Ordering::Relaxed);
+        self.dropped_samples.store(0, Ordering::Relaxed);
+        if let Some(secondary) = &self.secondary {
+            secondary.reset();
+        }
+    }
+}
+
+/// Optional per-session settings - sent from frontend
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SessionOptions {
+    /// Enable voice activity detection and write each speech segment to its own file
+    pub vad: Option<VadConfig>,
+    /// Audio to keep from before `start_recording` and prepend to the file (0-2000ms)
+    pub pre_roll_ms: Option<u32>,
+    /// File format for the recording (defaults to WAV)
+    pub format: Option<RecordingFormat>,
+    /// Switch to the default input device if the selected one disconnects
+    pub failover_to_default: Option<bool>,
+    /// Write 16kHz mono 16-bit PCM, which local transcription uses without converting.
+    /// The explicit output options below take precedence over this preset.
+    pub model_ready: Option<bool>,
+    /// Resample to this rate on the writer thread before encoding
+    pub output_sample_rate: Option<u32>,
+    /// Mix down (1) or remap to this many channels before encoding
+    pub output_channels: Option<u16>,
+    /// WAV sample encoding (also picks the FLAC bit depth); defaults to 32-bit float
+    pub sample_format: Option<WavSampleFormat>,
+    /// Second input captured alongside the main device, e.g. a PulseAudio/PipeWire
+    /// monitor source for system audio
+    pub secondary_device: Option<String>,
+    /// How the second input is combined with the main one (defaults to mixed mono)
+    pub source_layout: Option<SourceLayout>,
+    /// Stop on its own once this much audio has been recorded (pauses excluded)
+    pub max_duration_seconds: Option<f32>,
+    /// Stop on its own after sustained silence
+    pub silence_timeout: Option<SilenceTimeout>,
+    /// Device channels to record, in order; each becomes one captured channel.
+    /// Records every channel as-is when absent.
+    pub input_channels: Option<Vec<ChannelSelection>>,
+    /// High-pass, noise suppression and AGC applied before writing
+    pub dsp: Option<DspConfig>,
+    /// Transcribe in chunks while recording instead of only after the file is finished
+    pub streaming: Option<StreamingConfig>,
+    /// Split the recording into numbered files by duration or size.
+    /// The raw copy, if kept, stays a single file.
+    pub rotation: Option<RotationConfig>,
+}
+
+/// One device input channel to record - sent from frontend
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChannelSelection {
+    /// Zero-based channel index on the device
+    pub channel: u16,
+    /// Gain applied to this channel, in dB
+    #[serde(default)]
+    pub gain_db: f32,
+}
+
+/// A selected device channel with its gain as a linear factor, used in the callback
+struct ChannelRoute {
+    channel: usize,
+    gain: f32,
+}
+
+/// Sample rate the local transcription models expect
+const MODEL_SAMPLE_RATE: u32 = 16000;
+
+/// Simple recorder commands for writer thread communication
 enum RecorderCmd {
     Start(mpsc::Sender<()>), // Response channel to confirm command processed
     Stop(mpsc::Sender<()>),  // Response channel to confirm command processed
+    Pause(mpsc::Sender<()>),
+    Resume(mpsc::Sender<()>),
+    /// Flag the current position, with an optional label
+    Marker(Option<String>, mpsc::Sender<RecordingMarker>),
+    /// Read captured audio from this input from now on, after failover.
+    /// Whatever is left in the old one is written first.
+    SwitchInput(Box<CaptureInput>),
     Shutdown,
 }
 
+/// Messages for the thread that owns the input stream
+#[derive(Debug)]
+enum StreamMsg {
+    Error(cpal::StreamError), // Reported by the stream's error callback
+    Stop,
+}
+
+/// Emitted when the input device disappears or stops delivering audio
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct DeviceDisconnected {
+    recording_id: String,
+    device_id: String,
+    reason: String,
+    /// Device the session switched to, if failover succeeded
+    failover_device_id: Option<String>,
+    failover_error: Option<String>,
+}
+
+/// Payload for the `recorder-auto-stopped` event - the recording is already finalized
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct AutoStopped {
+    recording_id: String,
+    reason: AutoStopReason,
+    recording: AudioRecording,
+}
+
+/// Payload for the `recorder-write-failed` event. Capture has stopped; `recording`
+/// is what reached the file, if it could still be finalized.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct WriteFailed {
+    recording_id: String,
+    error: RecorderError,
+    recording: Option<AudioRecording>,
+}
+
 /// Simplified recorder state
 pub struct RecorderState {
     cmd_tx: Option<mpsc::Sender<RecorderCmd>>,
-    worker_handle: Option<JoinHandle<()>>,
-    writer: Option<Arc<Mutex<WavWriter>>>,
+    /// One stream thread per input source
+    stream_txs: Vec<mpsc::Sender<StreamMsg>>,
+    worker_handles: Vec<JoinHandle<()>>,
+    writer_handle: Option<JoinHandle<()>>,
+    overruns: Option<Arc<OverrunCounters>>,
+    writer: Option<Arc<Mutex<RecordingWriter>>>,
+    raw_writer: Option<Arc<Mutex<RecordingWriter>>>,
+    streaming: Option<StreamingHandle>,
+    metadata: Option<Arc<Mutex<RecordingMetadata>>>,
+    vad: Option<Arc<Mutex<VadStage>>>,
+    input_level: Option<Arc<SharedLevel>>,
+    /// Phase of the open session; `None` means idle
+    status: Option<Arc<SessionStatus>>,
     is_recording: Arc<AtomicBool>,
+    /// Recording the writer thread finalized on its own, after an auto-stop or a
+    /// disk write failure; the next `stop_recording` returns it
+    finished: Arc<Mutex<Option<AudioRecording>>>,
+    /// Files the writer thread has rotated away from, in order
+    closed_segments: Arc<Mutex<Vec<RecordingSegment>>>,
+    paused_at: Option<Instant>,
+    paused_duration: Duration,
+    /// Estimated disk usage of the session, raw copy included
+    bytes_per_second: u64,
+    capture_sample_rate: u32,
+    capture_channels: u16,
     sample_rate: u32,
     channels: u16,
     file_path: Option<PathBuf>,
@@ -45,133 +227,445 @@ impl RecorderState {
     pub fn new() -> Self {
         Self {
             cmd_tx: None,
-            worker_handle: None,
+            stream_txs: Vec::new(),
+            worker_handles: Vec::new(),
+            writer_handle: None,
+            overruns: None,
             writer: None,
+            raw_writer: None,
+            streaming: None,
+            metadata: None,
+            vad: None,
+            input_level: None,
+            status: None,
             is_recording: Arc::new(AtomicBool::new(false)),
+            finished: Arc::new(Mutex::new(None)),
+            closed_segments: Arc::new(Mutex::new(Vec::new())),
+            paused_at: None,
+            paused_duration: Duration::ZERO,
+            bytes_per_second: 0,
+            capture_sample_rate: 0,
+            capture_channels: 0,
             sample_rate: 0,
             channels: 0,
             file_path: None,
         }
     }
 
-    /// List available recording devices by name
-    pub fn enumerate_devices(&self) -> Result<Vec<String>> {
-        let host = cpal::default_host();
-        let devices = host
-            .input_devices()
-            .map_err(|e| format!("Failed to get input devices: {}", e))?
-            .filter_map(|device| device.name().ok())
-            .collect();
-
-        Ok(devices)
-    }
-
     /// Initialize recording session - creates stream and WAV writer
     pub fn init_session(
         &mut self,
-        device_name: String,
+        device_identifier: String,
         output_folder: PathBuf,
         recording_id: String,
         preferred_sample_rate: Option<u32>,
+        options: SessionOptions,
+        app_handle: AppHandle,
     ) -> Result<()> {
         // Clean up any existing session
         self.close_session()?;
+        let metadata_options = options.clone();
 
         // Create file path
-        let file_path = output_folder.join(format!("{}.wav", recording_id));
+        let format = options.format.unwrap_or_default();
+        let file_path =
+            output_folder.join(format!("{}.{}", recording_id, format.extension()));
 
         // Find the device
         let host = cpal::default_host();
-        let device = find_device(&host, &device_name)?;
+        let device = device::find_device(&host, &device_identifier)?;
+
+        // Only the selected channels are captured, but the device has to be opened
+        // with enough channels to include all of them
+        let routes: Vec<ChannelRoute> = options
+            .input_channels
+            .iter()
+            .flatten()
+            .map(|selection| ChannelRoute {
+                channel: selection.channel as usize,
+                gain: 10f32.powf(selection.gain_db / 20.0),
+            })
+            .collect();
+        let min_channels = routes
+            .iter()
+            .map(|route| route.channel as u16 + 1)
+            .max()
+            .unwrap_or(1);
 
         // Get optimal config for voice with optional preferred sample rate
-        let config = get_optimal_config(&device, preferred_sample_rate)?;
-        let sample_format = config.sample_format();
+        let config = get_optimal_config(&device, preferred_sample_rate, min_channels)?;
         let sample_rate = config.sample_rate().0;
-        let channels = config.channels();
+        let selected_channels = if routes.is_empty() {
+            config.channels()
+        } else {
+            routes.len() as u16
+        };
 
-        // Create WAV writer
-        let writer = WavWriter::new(file_path.clone(), sample_rate, channels)
-            .map_err(|e| format!("Failed to create WAV file: {}", e))?;
+        // With a second source, each input is captured as mono and combined on the writer thread
+        let source_layout = options
+            .secondary_device
+            .as_ref()
+            .map(|_| options.source_layout.unwrap_or_default());
+        let (capture_channels, channels) = match source_layout {
+            Some(layout) => (1, layout.channels()),
+            None => (selected_channels, selected_channels),
+        };
+
+        // Resolve what actually gets written, which may differ from what's captured
+        let model_ready = options.model_ready.unwrap_or(false);
+        let output_rate = options
+            .output_sample_rate
+            .or(model_ready.then_some(MODEL_SAMPLE_RATE))
+            .unwrap_or(sample_rate);
+        let output_channels = options
+            .output_channels
+            .or(model_ready.then_some(1))
+            .unwrap_or(channels);
+        let wav_sample_format = options
+            .sample_format
+            .or(model_ready.then_some(WavSampleFormat::Pcm16))
+            .unwrap_or_default();
+        let converter = if output_rate != sample_rate || output_channels != channels {
+            Some(OutputConverter::new(
+                sample_rate,
+                channels,
+                output_rate,
+                output_channels,
+            )?)
+        } else {
+            None
+        };
+
+        // Refuse to start on a nearly full disk rather than fail a minute in
+        let keep_raw = matches!(&options.dsp, Some(config) if config.keep_raw);
+        let raw_bytes_per_second = if keep_raw {
+            format.bytes_per_second(sample_rate, channels, wav_sample_format)
+        } else {
+            0
+        };
+        let bytes_per_second =
+            format.bytes_per_second(output_rate, output_channels, wav_sample_format)
+                + raw_bytes_per_second;
+        if let Some(space) = DiskSpace::check(&output_folder, bytes_per_second) {
+            if space.estimated_minutes_remaining < MIN_MINUTES_REMAINING {
+                return Err(RecorderError::DiskWrite {
+                    message: format!(
+                        "Not enough free space in {:?}: {} MB left, about {:.1} minutes of audio",
+                        output_folder,
+                        space.available_bytes / 1_000_000,
+                        space.estimated_minutes_remaining
+                    ),
+                });
+            }
+            info!(
+                "{} MB free, about {:.0} minutes of audio",
+                space.available_bytes / 1_000_000,
+                space.estimated_minutes_remaining
+            );
+        }
+
+        // Create the file writer for the requested format
+        let mut writer = RecordingWriter::new(
+            format,
+            file_path.clone(),
+            output_rate,
+            output_channels,
+            wav_sample_format,
+        )
+        .map_err(|e| {
+            RecorderError::disk_write(e, format!("Failed to create {} file", format.extension()))
+        })?;
+        writer.set_title(recording_id.clone());
+        self.closed_segments = Arc::new(Mutex::new(Vec::new()));
+        let rotator = options.rotation.as_ref().and_then(|config| {
+            Rotator::new(
+                config,
+                &writer,
+                wav_sample_format,
+                self.closed_segments.clone(),
+                recording_id.clone(),
+                app_handle.clone(),
+            )
+        });
         let writer = Arc::new(Mutex::new(writer));
 
-        // Create stream config
-        let stream_config = cpal::StreamConfig {
-            channels,
-            sample_rate: cpal::SampleRate(sample_rate),
-            buffer_size: cpal::BufferSize::Default,
+        // Processing runs at the capture rate, before any output conversion
+        let dsp = options
+            .dsp
+            .as_ref()
+            .and_then(|config| DspChain::new(config, sample_rate, channels));
+        let raw_writer = match &options.dsp {
+            Some(config) if config.keep_raw && dsp.is_some() => {
+                let raw_folder = output_folder.join(RAW_FOLDER);
+                std::fs::create_dir_all(&raw_folder).map_err(|e| {
+                    RecorderError::disk_write(e, format!("Failed to create {:?}", raw_folder))
+                })?;
+                let raw_path = raw_folder.join(format!("{}.{}", recording_id, format.extension()));
+                let mut raw_writer = RecordingWriter::new(
+                    format,
+                    raw_path,
+                    sample_rate,
+                    channels,
+                    wav_sample_format,
+                )
+                .map_err(|e| {
+                    RecorderError::disk_write(
+                        e,
+                        format!("Failed to create raw {} file", format.extension()),
+                    )
+                })?;
+                raw_writer.set_title(format!("{} (raw)", recording_id));
+                Some(Arc::new(Mutex::new(raw_writer)))
+            }
+            _ => None,
+        };
+
+        // Streaming transcription gets the processed audio, chunked on the writer thread
+        let (chunker, streaming) = match options.streaming {
+            Some(config) => {
+                let (chunker, handle) = Chunker::new(
+                    config,
+                    sample_rate,
+                    channels,
+                    recording_id.clone(),
+                    app_handle.clone(),
+                )?;
+                (Some(chunker), Some(handle))
+            }
+            None => (None, None),
+        };
+
+        // Create the VAD stage if requested
+        let vad = match options.vad {
+            Some(vad_config) => Some(Arc::new(Mutex::new(VadStage::new(
+                vad_config,
+                sample_rate,
+                channels,
+                output_folder.clone(),
+                recording_id.clone(),
+                app_handle.clone(),
+            )?))),
+            None => None,
         };
 
         // Create fresh recording flag
         self.is_recording = Arc::new(AtomicBool::new(false));
         let is_recording = self.is_recording.clone();
+        self.finished = Arc::new(Mutex::new(None));
+
+        let (producer, consumer) = ring_buffer(sample_rate, capture_channels);
+        let secondary_overruns = Arc::new(OverrunCounters::default());
+        let overruns = Arc::new(OverrunCounters {
+            secondary: source_layout.map(|_| secondary_overruns.clone()),
+            ..Default::default()
+        });
+        let callback = Arc::new(CallbackShared {
+            overruns: overruns.clone(),
+            heartbeat: AtomicU64::new(0),
+            routes,
+        });
+
+        // The second source gets its own stream and ring buffer; its audio is
+        // resampled to the main input's rate if its device can't match it
+        let mut mixer = None;
+        let mut secondary = None;
+        if let (Some(secondary_id), Some(layout)) = (&options.secondary_device, source_layout) {
+            let secondary_device = device::find_device(&host, secondary_id)?;
+            let secondary_config = get_optimal_config(&secondary_device, Some(sample_rate), 1)?;
+            let secondary_rate = secondary_config.sample_rate().0;
+            let (producer, consumer) = ring_buffer(secondary_rate, 1);
+            mixer = Some(SourceMixer::new(layout, consumer, secondary_rate, sample_rate)?);
+            let callback = Arc::new(CallbackShared {
+                overruns: secondary_overruns,
+                heartbeat: AtomicU64::new(0),
+                routes: Vec::new(),
+            });
+            info!(
+                "Capturing second source '{}' at {} Hz ({:?})",
+                secondary_id, secondary_rate, layout
+            );
+            secondary = Some((
+                secondary_id.clone(),
+                secondary_device,
+                secondary_config,
+                callback,
+                producer,
+            ));
+        }
+
+        // Described in a sidecar beside the file, updated on start and stop
+        let metadata = Arc::new(Mutex::new(RecordingMetadata {
+            format,
+            sample_rate: output_rate,
+            channels: output_channels,
+            capture_sample_rate: sample_rate,
+            capture_channels: channels,
+            device_id: device_identifier.clone(),
+            device_name: device.name().ok(),
+            ..RecordingMetadata::new(recording_id.clone(), &file_path, metadata_options)
+        }));
 
-        // Create command channel for worker thread
+        let status = Arc::new(SessionStatus::new(
+            recording_id.clone(),
+            device_identifier.clone(),
+            app_handle.clone(),
+        ));
+
+        // Create command channel for the writer thread
         let (cmd_tx, cmd_rx) = mpsc::channel();
 
-        // Clone for the worker thread
-        let writer_clone = writer.clone();
-        let is_recording_clone = is_recording.clone();
-
-        // Create the worker thread that owns the stream
-        let worker = thread::spawn(move || {
-            // Build the stream IN this thread (required for macOS)
-            let stream = match build_input_stream(
-                &device,
-                &stream_config,
-                sample_format,
-                is_recording_clone,
-                writer_clone,
-            ) {
-                Ok(s) => s,
-                Err(e) => {
-                    error!("Failed to build stream: {}", e);
-                    return;
-                }
-            };
+        // Level metering runs for the whole session so the UI can show a mic test meter
+        let input_level = Arc::new(SharedLevel::default());
 
-            // Start the stream
-            if let Err(e) = stream.play() {
-                error!("Failed to start stream: {}", e);
-                return;
-            }
+        // Everything fed with captured audio, owned by the writer thread
+        let sink = InputSink {
+            is_recording: is_recording.clone(),
+            output: OutputPath {
+                writer: writer.clone(),
+                raw_writer: raw_writer.clone(),
+                dsp,
+                converter,
+                chunker,
+                rotator,
+                quality: QualityMeter::new(sample_rate, channels),
+                frames: 0,
+                channels,
+                sample_rate,
+                output_rate,
+            },
+            vad: vad.clone(),
+            meter: LevelMeter::new(
+                sample_rate,
+                channels,
+                input_level.clone(),
+                recording_id.clone(),
+                app_handle.clone(),
+            ),
+            pre_roll: PreRollBuffer::new(
+                options.pre_roll_ms.unwrap_or(0),
+                sample_rate,
+                channels,
+            ),
+            mixer,
+            auto_stop: AutoStop::new(
+                options.max_duration_seconds,
+                options.silence_timeout,
+                sample_rate,
+                channels,
+            ),
+            overruns: overruns.clone(),
+            paused_at: None,
+            paused_duration: Duration::ZERO,
+            metadata: metadata.clone(),
+            status: status.clone(),
+            finished: self.finished.clone(),
+            closed_segments: self.closed_segments.clone(),
+            recording_id: recording_id.clone(),
+            app_handle: app_handle.clone(),
+        };
 
-            info!("Audio stream started successfully");
+        // Create the writer thread that drains the ring buffer into the sink
+        let writer_worker = {
+            let overruns = overruns.clone();
+            let input = CaptureInput {
+                consumer,
+                resampler: None,
+            };
+            thread::spawn(move || run_writer(input, cmd_rx, sink, capture_channels, overruns))
+        };
 
-            // Keep thread alive by waiting for commands
-            // This blocks but is responsive - no sleeping!
-            loop {
-                match cmd_rx.recv() {
-                    Ok(RecorderCmd::Start(reply_tx)) => {
-                        is_recording.store(true, Ordering::Relaxed);
-                        info!("Recording started");
-                        let _ = reply_tx.send(()); // Confirm command processed
-                    }
-                    Ok(RecorderCmd::Stop(reply_tx)) => {
-                        is_recording.store(false, Ordering::Relaxed);
-                        info!("Recording stopped");
-                        let _ = reply_tx.send(()); // Confirm command processed
-                    }
-                    Ok(RecorderCmd::Shutdown) | Err(_) => {
-                        info!("Shutting down audio worker");
-                        break;
-                    }
-                }
-            }
-            // Stream automatically drops here
-        });
+        // Create the worker threads that own the streams and watch them for failures
+        let (ready_tx, ready_rx) = mpsc::channel::<Result<()>>();
+        let (stream_tx, stream_rx) = mpsc::channel::<StreamMsg>();
+        let supervisor = StreamSupervisor {
+            status: Some(status.clone()),
+            recording_id: recording_id.clone(),
+            device_id: device_identifier,
+            sample_rate,
+            channels: capture_channels,
+            failover_to_default: options.failover_to_default.unwrap_or(false),
+            callback,
+            cmd_tx: Some(cmd_tx.clone()),
+            stream_tx: stream_tx.clone(),
+            app_handle: app_handle.clone(),
+        };
+        self.stream_txs.push(stream_tx);
+        let primary_ready_tx = ready_tx.clone();
+        self.worker_handles.push(thread::spawn(move || {
+            supervisor.run(device, config, producer, stream_rx, primary_ready_tx)
+        }));
+
+        if let Some((secondary_id, secondary_device, secondary_config, callback, producer)) =
+            secondary
+        {
+            let (stream_tx, stream_rx) = mpsc::channel::<StreamMsg>();
+            let supervisor = StreamSupervisor {
+                // Losing the second source leaves the main recording intact
+                status: None,
+                recording_id: recording_id.clone(),
+                device_id: secondary_id,
+                sample_rate: secondary_config.sample_rate().0,
+                channels: 1,
+                // The default input is a microphone, not a stand-in for system audio
+                failover_to_default: false,
+                callback,
+                cmd_tx: None,
+                stream_tx: stream_tx.clone(),
+                app_handle,
+            };
+            self.stream_txs.push(stream_tx);
+            let secondary_ready_tx = ready_tx.clone();
+            self.worker_handles.push(thread::spawn(move || {
+                supervisor.run(
+                    secondary_device,
+                    secondary_config,
+                    producer,
+                    stream_rx,
+                    secondary_ready_tx,
+                )
+            }));
+        }
+        drop(ready_tx);
 
         // Store everything
         self.cmd_tx = Some(cmd_tx);
-        self.worker_handle = Some(worker);
+        self.writer_handle = Some(writer_worker);
+        self.overruns = Some(overruns);
         self.writer = Some(writer);
-        self.sample_rate = sample_rate;
-        self.channels = channels;
+        self.raw_writer = raw_writer;
+        self.streaming = streaming;
+        self.metadata = Some(metadata);
+        self.vad = vad;
+        self.input_level = Some(input_level);
+        self.status = Some(status.clone());
+        self.bytes_per_second = bytes_per_second;
+        self.capture_sample_rate = sample_rate;
+        self.capture_channels = channels;
+        self.sample_rate = output_rate;
+        self.channels = output_channels;
         self.file_path = Some(file_path);
 
+        // Each stream thread replies once its stream is running or has failed to start;
+        // a thread that exits without replying has panicked
+        for _ in 0..self.worker_handles.len() {
+            let ready = ready_rx.recv().unwrap_or_else(|_| {
+                Err(RecorderError::Internal {
+                    message: "Audio stream thread exited before starting".to_string(),
+                })
+            });
+            if let Err(e) = ready {
+                error!("Recording session {} failed to start: {}", recording_id, e);
+                let _ = self.close_session();
+                return Err(e);
+            }
+        }
+
+        // The session is usable from here on
+        status.transition(RecorderPhase::SessionReady)?;
+
         info!(
-            "Recording session initialized: {} Hz, {} channels, file: {:?}",
-            sample_rate, channels, self.file_path
+            "Recording session initialized: {} Hz, {} channels (writing {} Hz, {} channels), file: {:?}",
+            sample_rate, channels, output_rate, output_channels, self.file_path
         );
 
         Ok(())
@@ -179,59 +673,128 @@ impl RecorderState {
 
     /// Start recording - send command to worker thread and wait for confirmation
     pub fn start_recording(&mut self) -> Result<()> {
-        if let Some(tx) = &self.cmd_tx {
-            let (reply_tx, reply_rx) = mpsc::channel();
-            tx.send(RecorderCmd::Start(reply_tx))
-                .map_err(|e| format!("Failed to send start command: {}", e))?;
-            // Wait for worker thread to confirm the command was processed
-            reply_rx
-                .recv()
-                .map_err(|e| format!("Failed to receive start confirmation: {}", e))?;
-        } else {
-            return Err("No recording session initialized".to_string());
+        let status = self.status()?;
+        // Paused -> Recording is allowed for resume, but starting over mid-file
+        // would splice in the pre-roll and reset the recording's counters
+        let phase = status.phase();
+        if phase != RecorderPhase::SessionReady {
+            return Err(RecorderError::InvalidState {
+                message: format!("Cannot start recording while {:?}", phase),
+            });
+        }
+        self.send_command(RecorderCmd::Start, "start")?;
+        // A pre-roll longer than the duration limit completes the recording right away
+        if self.has_finished() {
+            return Ok(());
+        }
+        status.transition(RecorderPhase::Recording)?;
+        self.paused_at = None;
+        self.paused_duration = Duration::ZERO;
+
+        if let (Some(metadata), Some(file_path)) = (&self.metadata, &self.file_path) {
+            if let Ok(mut m) = metadata.lock() {
+                m.start();
+                if let Err(e) = m.write(file_path) {
+                    warn!("{}", e);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Pause recording - samples are dropped until resumed, the file stays open
+    pub fn pause_recording(&mut self) -> Result<()> {
+        let status = self.status()?;
+        status.check(RecorderPhase::Paused)?;
+        self.send_command(RecorderCmd::Pause, "pause")?;
+        status.transition(RecorderPhase::Paused)?;
+        self.paused_at = Some(Instant::now());
+        Ok(())
+    }
+
+    /// Resume a paused recording - appends to the same file
+    pub fn resume_recording(&mut self) -> Result<()> {
+        let status = self.status()?;
+        if status.phase() != RecorderPhase::Paused {
+            return Err(RecorderError::InvalidState {
+                message: "Recording is not paused".to_string(),
+            });
+        }
+        self.send_command(RecorderCmd::Resume, "resume")?;
+        status.transition(RecorderPhase::Recording)?;
+        if let Some(paused_at) = self.paused_at.take() {
+            self.paused_duration += paused_at.elapsed();
         }
         Ok(())
     }
 
     /// Stop recording - return file info
     pub fn stop_recording(&mut self) -> Result<AudioRecording> {
-        // Send stop command to worker thread and wait for confirmation
-        if let Some(tx) = &self.cmd_tx {
-            let (reply_tx, reply_rx) = mpsc::channel();
-            tx.send(RecorderCmd::Stop(reply_tx))
-                .map_err(|e| format!("Failed to send stop command: {}", e))?;
-            // Wait for worker thread to confirm the command was processed
-            reply_rx
-                .recv()
-                .map_err(|e| format!("Failed to receive stop confirmation: {}", e))?;
-        }
-
-        // Finalize the WAV file and get metadata
-        let (sample_rate, channels, duration) = if let Some(writer) = &self.writer {
-            let mut w = writer
-                .lock()
-                .map_err(|e| format!("Failed to lock writer: {}", e))?;
-            w.finalize()
-                .map_err(|e| format!("Failed to finalize WAV: {}", e))?;
-            w.get_metadata()
-        } else {
-            (self.sample_rate, self.channels, 0.0)
-        };
+        let status = self.status()?;
+        status.transition(RecorderPhase::Finalizing)?;
+        match self.finalize_recording() {
+            Ok(recording) => {
+                status.transition(RecorderPhase::SessionReady)?;
+                Ok(recording)
+            }
+            Err(e) => {
+                status.fail(e.to_string());
+                Err(e)
+            }
+        }
+    }
 
-        let file_path = self
-            .file_path
-            .as_ref()
-            .map(|p| p.to_string_lossy().to_string());
+    fn finalize_recording(&mut self) -> Result<AudioRecording> {
+        // Wait for the writer thread to write out everything captured so far,
+        // unless it already stopped on its own
+        if !self.has_finished() {
+            self.send_command(RecorderCmd::Stop, "stop")?;
+        }
 
-        info!("Recording stopped: {:.2}s, file: {:?}", duration, file_path);
+        // Stopping while paused closes out the final pause
+        if let Some(paused_at) = self.paused_at.take() {
+            self.paused_duration += paused_at.elapsed();
+        }
 
-        Ok(AudioRecording {
-            audio_data: Vec::new(), // Empty for file-based recording
-            sample_rate,
-            channels,
-            duration_seconds: duration,
-            file_path,
-        })
+        // After an auto-stop or a write failure the file was already finalized,
+        // possibly while the stop command was on its way
+        if let Some(recording) = self.finished.lock().ok().and_then(|mut f| f.take()) {
+            return Ok(recording);
+        }
+
+        // Finalize the recording file and get metadata
+        let (Some(writer), Some(overruns), Some(metadata)) =
+            (&self.writer, &self.overruns, &self.metadata)
+        else {
+            return Err(no_session());
+        };
+        finish_recording(
+            writer,
+            self.raw_writer.as_deref(),
+            overruns,
+            self.paused_duration,
+            metadata,
+            &self.closed_segments,
+            None,
+        )
+    }
+
+    /// Streaming transcription of the session, if it has one. Its transcript is
+    /// collected with the recorder unlocked, as the last chunk may take a while.
+    pub fn streaming(&self) -> Option<StreamingHandle> {
+        self.streaming.clone()
+    }
+
+    /// Flag the current position of the recording. The marker is placed after
+    /// all audio captured so far, and embedded in WAV files as a cue point.
+    pub fn add_marker(&mut self, label: Option<String>) -> Result<RecordingMarker> {
+        let phase = self.status()?.phase();
+        if !matches!(phase, RecorderPhase::Recording | RecorderPhase::Paused) {
+            return Err(RecorderError::InvalidState {
+                message: format!("Cannot add a marker while {:?}", phase),
+            });
+        }
+        self.send_command(|reply_tx| RecorderCmd::Marker(label, reply_tx), "marker")
     }
 
     /// Cancel recording - stop and delete the file
@@ -243,39 +806,87 @@ impl RecorderState {
             let _ = reply_rx.recv(); // Wait for confirmation but ignore errors during cancel
         }
 
-        // Delete the file if it exists
+        // Delete the file and its sidecar if they exist
         if let Some(file_path) = &self.file_path {
             std::fs::remove_file(file_path).ok(); // Ignore errors
+            std::fs::remove_file(metadata::sidecar_path(file_path)).ok();
             debug!("Deleted recording file: {:?}", file_path);
         }
+        // Along with every later segment and the raw copy
+        if let Ok(closed) = self.closed_segments.lock() {
+            for segment in closed.iter() {
+                std::fs::remove_file(&segment.file_path).ok();
+            }
+        }
+        for writer in [&self.writer, &self.raw_writer].into_iter().flatten() {
+            if let Ok(w) = writer.lock() {
+                std::fs::remove_file(w.get_file_path()).ok();
+            }
+        }
+
+        // Taken out first so closing the session doesn't announce the open speech segment
+        let vad = self.vad.take();
 
         // Clear the session
         self.close_session()?;
 
+        // The writer thread has exited, so no further speech segments can appear
+        if let Some(vad) = vad {
+            if let Ok(mut v) = vad.lock() {
+                v.discard();
+            }
+        }
+
         Ok(())
     }
 
     /// Close the recording session
     pub fn close_session(&mut self) -> Result<()> {
-        // Send shutdown command to worker thread
+        // Stop the streams first so no more samples arrive
+        for tx in self.stream_txs.drain(..) {
+            let _ = tx.send(StreamMsg::Stop);
+        }
+        for handle in self.worker_handles.drain(..) {
+            let _ = handle.join();
+        }
+
+        // Then let the writer thread drain what's left and exit
         if let Some(tx) = self.cmd_tx.take() {
             let _ = tx.send(RecorderCmd::Shutdown);
         }
-
-        // Wait for worker thread to finish
-        if let Some(handle) = self.worker_handle.take() {
+        if let Some(handle) = self.writer_handle.take() {
             let _ = handle.join();
         }
 
-        // Finalize and drop the writer
-        if let Some(writer) = self.writer.take() {
+        // Finalize and drop the writers
+        for writer in [self.writer.take(), self.raw_writer.take()].into_iter().flatten() {
             if let Ok(mut w) = writer.lock() {
                 let _ = w.finalize(); // Ignore errors during cleanup
             }
         }
 
+        // Finish any speech segment that was still open
+        if let Some(vad) = self.vad.take() {
+            if let Ok(mut v) = vad.lock() {
+                v.flush();
+            }
+        }
+
+        if let Some(status) = self.status.take() {
+            let _ = status.transition(RecorderPhase::Idle);
+        }
+
         // Clear state
+        self.streaming = None;
+        self.metadata = None;
+        self.input_level = None;
+        self.overruns = None;
+        self.paused_at = None;
+        self.paused_duration = Duration::ZERO;
+        self.bytes_per_second = 0;
         self.file_path = None;
+        self.capture_sample_rate = 0;
+        self.capture_channels = 0;
         self.sample_rate = 0;
         self.channels = 0;
 
@@ -283,74 +894,273 @@ impl RecorderState {
         Ok(())
     }
 
-    /// Get current recording ID if actively recording
+    /// Get current recording ID if actively recording (including while paused)
     pub fn get_current_recording_id(&self) -> Option<String> {
-        if self.is_recording.load(Ordering::Acquire) {
-            self.file_path
+        let status = self.status.as_ref()?;
+        matches!(
+            status.phase(),
+            RecorderPhase::Recording | RecorderPhase::Paused
+        )
+        .then(|| status.recording_id().to_string())
+    }
+
+    /// Describe the session: phase, device, formats and progress
+    pub fn get_status(&self) -> RecorderStatus {
+        let Some(status) = &self.status else {
+            return RecorderStatus::idle();
+        };
+
+        // Rotated files count too: the current one is only the latest segment
+        let closed = self
+            .closed_segments
+            .lock()
+            .map(|closed| closed.clone())
+            .unwrap_or_default();
+        let mut paths: Vec<PathBuf> = closed.iter().map(|s| PathBuf::from(&s.file_path)).collect();
+        let mut elapsed_seconds: f32 = closed.iter().map(|s| s.duration_seconds).sum();
+        let mut format = None;
+        if let Some(Ok(w)) = self.writer.as_ref().map(|w| w.lock()) {
+            elapsed_seconds += w.get_duration_seconds();
+            format = Some(w.format());
+            paths.push(w.get_file_path().clone());
+        }
+        let paused = self.paused_duration + self.paused_at.map_or(Duration::ZERO, |t| t.elapsed());
+        let bytes_written = paths
+            .iter()
+            .filter_map(|path| std::fs::metadata(path).ok())
+            .map(|metadata| metadata.len())
+            .sum();
+
+        RecorderStatus {
+            recording_id: Some(status.recording_id().to_string()),
+            phase: status.phase(),
+            device_id: Some(status.device_id()),
+            capture_sample_rate: self.capture_sample_rate,
+            capture_channels: self.capture_channels,
+            sample_rate: self.sample_rate,
+            channels: self.channels,
+            format,
+            file_path: self
+                .file_path
                 .as_ref()
-                .and_then(|path| path.file_stem())
-                .and_then(|stem| stem.to_str())
-                .map(|s| s.to_string())
-        } else {
-            None
+                .map(|p| p.to_string_lossy().to_string()),
+            elapsed_seconds,
+            paused_seconds: paused.as_secs_f32(),
+            bytes_written,
+            disk_space: self.disk_space(),
+            error: status.error(),
+        }
+    }
+
+    /// Free space left where the session writes, and how long it lasts at the session's rate
+    pub fn disk_space(&self) -> Option<DiskSpace> {
+        let folder = self.file_path.as_ref()?.parent()?;
+        DiskSpace::check(folder, self.bytes_per_second)
+    }
+
+    /// Path of the file the current session writes to, if a session is open
+    pub fn get_session_file_path(&self) -> Option<&PathBuf> {
+        self.file_path.as_ref()
+    }
+
+    /// Get the most recent input level while a session is active
+    pub fn get_input_level(&self) -> Option<InputLevel> {
+        self.input_level
+            .as_ref()
+            .map(|level| level.load(self.is_recording.load(Ordering::Relaxed)))
+    }
+
+    fn status(&self) -> Result<Arc<SessionStatus>> {
+        self.status.clone().ok_or_else(no_session)
+    }
+
+    fn has_finished(&self) -> bool {
+        self.finished.lock().is_ok_and(|f| f.is_some())
+    }
+
+    /// Send a command to the writer thread and wait until it has been applied.
+    /// A writer thread that can't be reached has died, which fails the session.
+    fn send_command<T>(
+        &self,
+        cmd: impl FnOnce(mpsc::Sender<T>) -> RecorderCmd,
+        name: &str,
+    ) -> Result<T> {
+        let tx = self.cmd_tx.as_ref().ok_or_else(no_session)?;
+        let (reply_tx, reply_rx) = mpsc::channel();
+        let result = tx
+            .send(cmd(reply_tx))
+            .map_err(|e| format!("Failed to send {} command: {}", name, e))
+            .and_then(|_| {
+                reply_rx
+                    .recv()
+                    .map_err(|e| format!("Failed to receive {} confirmation: {}", name, e))
+            })
+            .map_err(|message| RecorderError::Internal { message });
+        if let (Err(e), Some(status)) = (&result, &self.status) {
+            status.fail(e.to_string());
         }
+        result
     }
 }
 
-/// Find a recording device by name
-fn find_device(host: &cpal::Host, device_name: &str) -> Result<Device> {
-    // Handle "default" device
-    if device_name.to_lowercase() == "default" {
-        return host
-            .default_input_device()
-            .ok_or_else(|| "No default input device available".to_string());
+/// Finalize the recording file, describe it for the frontend and update its sidecar
+fn finish_recording(
+    writer: &Mutex<RecordingWriter>,
+    raw_writer: Option<&Mutex<RecordingWriter>>,
+    overruns: &OverrunCounters,
+    paused_duration: Duration,
+    metadata: &Mutex<RecordingMetadata>,
+    closed_segments: &Mutex<Vec<RecordingSegment>>,
+    auto_stop_reason: Option<AutoStopReason>,
+) -> Result<AudioRecording> {
+    let raw_file_path = match raw_writer {
+        Some(raw_writer) => {
+            let mut w = raw_writer.lock().map_err(|e| RecorderError::Internal {
+                message: format!("Failed to lock raw writer: {}", e),
+            })?;
+            w.finalize().map_err(|e| {
+                RecorderError::disk_write(e, "Failed to finalize raw recording".to_string())
+            })?;
+            Some(w.get_file_path().to_string_lossy().to_string())
+        }
+        None => None,
+    };
+
+    let mut w = writer.lock().map_err(|e| RecorderError::Internal {
+        message: format!("Failed to lock writer: {}", e),
+    })?;
+    w.finalize()
+        .map_err(|e| RecorderError::disk_write(e, "Failed to finalize recording".to_string()))?;
+    let (sample_rate, channels, _) = w.get_metadata();
+    let format = w.format();
+
+    // The file being written is the last segment; any earlier ones are already closed
+    let mut segments = closed_segments
+        .lock()
+        .map(|closed| closed.clone())
+        .unwrap_or_default();
+    segments.push(RecordingSegment {
+        index: segments.len() as u32 + 1,
+        file_path: w.get_file_path().to_string_lossy().to_string(),
+        duration_seconds: w.get_duration_seconds(),
+    });
+    let duration: f32 = segments.iter().map(|s| s.duration_seconds).sum();
+    let audio_path = PathBuf::from(&segments[0].file_path);
+    let file_path = Some(segments[0].file_path.clone());
+    let paused_seconds = paused_duration.as_secs_f32();
+
+    let overrun_count = overruns.count.load(Ordering::Relaxed);
+    let dropped_samples = overruns.dropped_samples.load(Ordering::Relaxed);
+    if overrun_count > 0 {
+        warn!(
+            "Recording had {} buffer overruns ({} samples dropped)",
+            overrun_count, dropped_samples
+        );
+    }
+    let secondary_overruns = overruns.secondary.as_deref().map(|secondary| {
+        (
+            secondary.count.load(Ordering::Relaxed),
+            secondary.dropped_samples.load(Ordering::Relaxed),
+        )
+    });
+    if let Some((count, dropped)) = secondary_overruns.filter(|(count, _)| *count > 0) {
+        warn!(
+            "Second source had {} buffer overruns ({} samples dropped)",
+            count, dropped
+        );
     }
 
-    // Find specific device
-    let devices: Vec<_> = host.input_devices().map_err(|e| e.to_string())?.collect();
+    info!(
+        "Recording stopped: {:.2}s ({:.2}s paused), file: {:?}",
+        duration, paused_seconds, file_path
+    );
 
-    for device in devices {
-        if let Ok(name) = device.name() {
-            if name == device_name {
-                return Ok(device);
-            }
+    let mut recording = AudioRecording {
+        audio_data: Vec::new(), // Empty for file-based recording
+        sample_rate,
+        channels,
+        duration_seconds: duration,
+        paused_seconds,
+        overrun_count,
+        dropped_samples,
+        secondary_overrun_count: secondary_overruns.map(|(count, _)| count),
+        secondary_dropped_samples: secondary_overruns.map(|(_, dropped)| dropped),
+        format,
+        file_extension: format.extension().to_string(),
+        file_path,
+        raw_file_path,
+        transcript: None,
+        markers: Vec::new(),
+        quality: None,
+        segments,
+    };
+
+    // The audio is safe on disk either way, so a failed sidecar is only logged
+    if let Ok(mut m) = metadata.lock() {
+        m.finish(&recording, auto_stop_reason);
+        recording.markers = m.markers.clone();
+        recording.quality = m.quality.clone();
+        if let Err(e) = m.write(&audio_path) {
+            warn!("{}", e);
         }
     }
 
-    Err(format!("Device '{}' not found", device_name))
+    Ok(recording)
 }
 
-/// Get optimal configuration for voice recording
+/// Get optimal configuration for voice recording.
+/// `min_channels` rules out configs too narrow to include the selected input channels.
 fn get_optimal_config(
     device: &Device,
     preferred_sample_rate: Option<u32>,
+    min_channels: u16,
 ) -> Result<cpal::SupportedStreamConfig> {
     // Use preferred sample rate or default to 16kHz for voice
     let target_sample_rate = preferred_sample_rate.unwrap_or(16000);
+    // Fewest channels that still cover the selection; mono without one
+    let min_channels = min_channels.max(1);
 
     let configs: Vec<_> = device
         .supported_input_configs()
-        .map_err(|e| e.to_string())?
+        .map_err(|e| RecorderError::UnsupportedConfig {
+            message: format!("Failed to query input configurations: {}", e),
+        })?
         .collect();
 
     if configs.is_empty() {
-        return Err("No supported input configurations".to_string());
+        return Err(RecorderError::UnsupportedConfig {
+            message: "No supported input configurations".to_string(),
+        });
     }
 
-    // Filter for supported sample formats only
-    let supported_formats = [SampleFormat::F32, SampleFormat::I16, SampleFormat::U16];
-    let compatible_configs: Vec<_> = configs
+    // Every format can be converted, so order by preference instead of filtering:
+    // the device's native (default) format first, then by precision
+    let native_format = device
+        .default_input_config()
+        .ok()
+        .map(|config| config.sample_format());
+    let mut compatible_configs: Vec<_> = configs
         .iter()
-        .filter(|config| supported_formats.contains(&config.sample_format()))
+        .filter(|config| format_rank(config.sample_format()).is_some())
+        .filter(|config| config.channels() >= min_channels)
         .collect();
+    compatible_configs.sort_by_key(|config| {
+        let format = config.sample_format();
+        (Some(format) != native_format, format_rank(format))
+    });
 
     if compatible_configs.is_empty() {
-        return Err("No configurations with supported sample formats (F32, I16, U16)".to_string());
+        return Err(RecorderError::UnsupportedConfig {
+            message: format!(
+                "No configurations with supported sample formats and at least {} channels",
+                min_channels
+            ),
+        });
     }
 
     // Try to find mono config with target sample rate and supported format
     for config in &compatible_configs {
-        if config.channels() == 1 {
+        if config.channels() == min_channels {
             let min_rate = config.min_sample_rate().0;
             let max_rate = config.max_sample_rate().0;
             if min_rate <= target_sample_rate && max_rate >= target_sample_rate {
@@ -374,7 +1184,7 @@ fn get_optimal_config(
 
     for config in &compatible_configs {
         // Prefer mono
-        if config.channels() == 1 {
+        if config.channels() == min_channels {
             let min_rate = config.min_sample_rate().0;
             let max_rate = config.max_sample_rate().0;
 
@@ -408,66 +1218,945 @@ fn get_optimal_config(
         best_config = Some(config.with_sample_rate(cpal::SampleRate(rate)));
     }
 
-    best_config.ok_or_else(|| "Failed to find suitable audio configuration".to_string())
+    best_config.ok_or_else(|| RecorderError::UnsupportedConfig {
+        message: "Failed to find suitable audio configuration".to_string(),
+    })
 }
 
-/// Build input stream for any supported sample format
-fn build_input_stream(
-    device: &Device,
-    config: &cpal::StreamConfig,
-    sample_format: SampleFormat,
+/// Preference among sample formats when the native one isn't available,
+/// highest precision first. `None` for formats the recorder can't convert.
+fn format_rank(format: SampleFormat) -> Option<u8> {
+    Some(match format {
+        SampleFormat::F32 => 0,
+        SampleFormat::F64 => 1,
+        SampleFormat::I32 => 2,
+        SampleFormat::I24 => 3,
+        SampleFormat::I64 => 4,
+        SampleFormat::U32 => 5,
+        SampleFormat::U64 => 6,
+        SampleFormat::I16 => 7,
+        SampleFormat::U16 => 8,
+        SampleFormat::I8 => 9,
+        SampleFormat::U8 => 10,
+        _ => return None,
+    })
+}
+
+/// How often the writer thread drains the ring buffer when idle
+const DRAIN_INTERVAL: Duration = Duration::from_millis(10);
+
+/// Everything fed with captured audio, owned by the writer thread
+struct InputSink {
     is_recording: Arc<AtomicBool>,
-    writer: Arc<Mutex<WavWriter>>,
-) -> Result<Stream> {
-    let err_fn = |err| error!("Audio stream error: {}", err);
+    output: OutputPath,
+    vad: Option<Arc<Mutex<VadStage>>>,
+    meter: LevelMeter,
+    pre_roll: PreRollBuffer,
+    /// Combines the main input with a second source, when the session has one
+    mixer: Option<SourceMixer>,
+    /// Duration and silence limits, when the session has any
+    auto_stop: Option<AutoStop>,
+    overruns: Arc<OverrunCounters>,
+    /// Pause time tracked here too, so an auto-stop can report it
+    paused_at: Option<Instant>,
+    paused_duration: Duration,
+    metadata: Arc<Mutex<RecordingMetadata>>,
+    status: Arc<SessionStatus>,
+    /// Where a recording finalized on its own is left for `stop_recording`
+    finished: Arc<Mutex<Option<AudioRecording>>>,
+    closed_segments: Arc<Mutex<Vec<RecordingSegment>>>,
+    recording_id: String,
+    app_handle: AppHandle,
+}
 
-    let stream = match sample_format {
-        SampleFormat::F32 => device
-            .build_input_stream(
-                config,
-                move |data: &[f32], _: &_| {
-                    if is_recording.load(Ordering::Relaxed) {
-                        if let Ok(mut w) = writer.lock() {
-                            let _ = w.write_samples_f32(data);
-                        }
+impl InputSink {
+    /// Handle a block of interleaved samples drained from the ring buffer
+    fn process(&mut self, data: &[f32]) {
+        let data = match self.mixer.as_mut() {
+            Some(mixer) => mixer.mix(data),
+            None => data,
+        };
+        let is_recording = self.is_recording.load(Ordering::Relaxed);
+
+        self.meter.process_samples(data, is_recording);
+
+        // VAD listens for the whole session, not just while recording
+        if let Some(vad) = &self.vad {
+            if let Ok(mut v) = vad.lock() {
+                v.process_samples(data);
+            }
+        }
+
+        if !is_recording {
+            self.pre_roll.push(data);
+            return;
+        }
+
+        let (result, auto_stop) = write_limited(&mut self.output, self.auto_stop.as_mut(), data);
+        self.handle_written(result, auto_stop);
+    }
+
+    /// Stop if the block just written failed or reached a limit
+    fn handle_written(&mut self, result: io::Result<()>, auto_stop: Option<AutoStopReason>) {
+        match (result, auto_stop) {
+            (Err(e), _) => self.write_failed(e),
+            (Ok(()), Some(reason)) => self.auto_stop(reason),
+            (Ok(()), None) => {}
+        }
+    }
+
+    fn has_finished(&self) -> bool {
+        self.finished.lock().is_ok_and(|f| f.is_some())
+    }
+
+    /// Begin writing, starting with whatever the pre-roll buffer holds
+    fn start(&mut self) {
+        // A recording that stopped on its own but was never collected is superseded
+        if let Ok(mut finished) = self.finished.lock() {
+            *finished = None;
+        }
+        if let Some(auto_stop) = self.auto_stop.as_mut() {
+            auto_stop.reset();
+        }
+        self.paused_at = None;
+        self.paused_duration = Duration::ZERO;
+        if let Some(chunker) = self.output.chunker.as_mut() {
+            chunker.reset();
+        }
+        self.output.quality.reset();
+
+        // The pre-roll is part of the recording, so it counts toward the limits too
+        let (head, tail) = self.pre_roll.as_slices();
+        let (mut result, mut auto_stop) =
+            write_limited(&mut self.output, self.auto_stop.as_mut(), head);
+        if result.is_ok() && auto_stop.is_none() {
+            (result, auto_stop) = write_limited(&mut self.output, self.auto_stop.as_mut(), tail);
+        }
+        self.pre_roll.clear();
+        self.is_recording.store(true, Ordering::Relaxed);
+        self.handle_written(result, auto_stop);
+    }
+
+    /// Stop writing and push out whatever processing and conversion still buffer
+    fn stop(&mut self) {
+        self.is_recording.store(false, Ordering::Relaxed);
+        self.output.flush();
+
+        // Left in the metadata for `finish_recording` to pick up
+        let quality = self.output.quality.summary();
+        info!(
+            "Recording quality: peak {:.1} dBFS, loudness {:?} LUFS, {} clipped samples, {:.0}% silent",
+            quality.peak_db,
+            quality.loudness_lufs,
+            quality.clipped_samples,
+            quality.silence_ratio * 100.0
+        );
+        if let Ok(mut m) = self.metadata.lock() {
+            m.quality = Some(quality);
+        }
+    }
+
+    /// Stop writing but keep the file open for `resume`
+    fn pause(&mut self) {
+        self.is_recording.store(false, Ordering::Relaxed);
+        self.paused_at = Some(Instant::now());
+    }
+
+    fn resume(&mut self) {
+        if let Some(paused_at) = self.paused_at.take() {
+            self.paused_duration += paused_at.elapsed();
+        }
+        self.is_recording.store(true, Ordering::Relaxed);
+    }
+
+    /// Flag the current position in every file being written
+    fn add_marker(&mut self, label: Option<String>) -> RecordingMarker {
+        let frame = self.output.position();
+        let mut metadata = self
+            .metadata
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        let id = metadata.markers.len() as u32 + 1;
+        let marker = RecordingMarker {
+            id,
+            label: label.unwrap_or_else(|| format!("Marker {}", id)),
+            frame,
+            seconds: frame as f32 / self.output.output_rate.max(1) as f32,
+        };
+        info!("Added marker {} at {:.2}s", id, marker.seconds);
+
+        if let Some(raw_writer) = &self.output.raw_writer {
+            if let Ok(mut w) = raw_writer.lock() {
+                w.add_marker(self.output.frames, marker.label.clone());
+            }
+        }
+        // Cue points are relative to the segment being written
+        let segment_frame = self.output.rotator.as_ref().map_or(frame, |rotator| {
+            frame.saturating_sub(rotator.segment_start())
+        });
+        let audio_path = match self.output.writer.lock() {
+            Ok(mut w) => {
+                w.add_marker(segment_frame, marker.label.clone());
+                // The sidecar is named after the first segment
+                Some(w.get_file_path().with_file_name(&metadata.file_name))
+            }
+            Err(_) => None,
+        };
+
+        // Keep the sidecar current so markers survive a crash
+        metadata.markers.push(marker.clone());
+        if let Some(audio_path) = audio_path {
+            if let Err(e) = metadata.write(&audio_path) {
+                warn!("{}", e);
+            }
+        }
+        marker
+    }
+
+    /// A session limit was reached: finalize the file and hand the recording to
+    /// the frontend. A later `stop_recording` returns the same recording.
+    fn auto_stop(&mut self, reason: AutoStopReason) {
+        self.stop();
+        info!("Recording auto-stopped: {:?}", reason);
+        if let Err(e) = self.status.transition(RecorderPhase::Finalizing) {
+            warn!("Auto-stop while not recording: {}", e);
+        }
+        if let Some(chunker) = &self.output.chunker {
+            chunker.finish();
+        }
+
+        match finish_recording(
+            &self.output.writer,
+            self.output.raw_writer.as_deref(),
+            &self.overruns,
+            self.paused_duration,
+            &self.metadata,
+            &self.closed_segments,
+            Some(reason),
+        ) {
+            Ok(recording) => {
+                if let Ok(mut finished) = self.finished.lock() {
+                    *finished = Some(recording.clone());
+                }
+                let _ = self.status.transition(RecorderPhase::SessionReady);
+                let _ = self.app_handle.emit(
+                    "recorder-auto-stopped",
+                    AutoStopped {
+                        recording_id: self.recording_id.clone(),
+                        reason,
+                        recording,
+                    },
+                );
+            }
+            Err(e) => {
+                error!("Failed to finalize auto-stopped recording: {}", e);
+                self.status.fail(e.to_string());
+            }
+        }
+    }
+
+    /// Writing to disk failed (disk full, access revoked): stop capturing rather
+    /// than keep "recording" into nothing, keep what reached the file and fail the session
+    fn write_failed(&mut self, error: io::Error) {
+        let error = RecorderError::disk_write(error, "Failed to write recording".to_string());
+        error!("Recording {} stopped: {}", self.recording_id, error);
+        self.stop();
+        if let Some(chunker) = &self.output.chunker {
+            chunker.finish();
+        }
+        if let Ok(mut m) = self.metadata.lock() {
+            m.write_error = Some(error.to_string());
+        }
+
+        // The file's headers still need a few bytes, which may or may not fit
+        let recording = match finish_recording(
+            &self.output.writer,
+            self.output.raw_writer.as_deref(),
+            &self.overruns,
+            self.paused_duration,
+            &self.metadata,
+            &self.closed_segments,
+            None,
+        ) {
+            Ok(recording) => Some(recording),
+            Err(e) => {
+                error!("Failed to finalize recording after a write failure: {}", e);
+                None
+            }
+        };
+        if let (Some(recording), Ok(mut finished)) = (&recording, self.finished.lock()) {
+            *finished = Some(recording.clone());
+        }
+
+        self.status.fail(error.to_string());
+        let _ = self.app_handle.emit(
+            "recorder-write-failed",
+            WriteFailed {
+                recording_id: self.recording_id.clone(),
+                error,
+                recording,
+            },
+        );
+    }
+}
+
+/// Write a block, cut short where it reaches the session's duration limit.
+/// Returns the write result and why to stop, if a limit was reached.
+fn write_limited(
+    output: &mut OutputPath,
+    auto_stop: Option<&mut AutoStop>,
+    data: &[f32],
+) -> (io::Result<()>, Option<AutoStopReason>) {
+    match auto_stop.and_then(|a| a.process(data)) {
+        Some((reason, keep)) => (output.write(&data[..keep]), Some(reason)),
+        None => (output.write(data), None),
+    }
+}
+
+/// Route from captured audio to the file(s) on disk
+struct OutputPath {
+    writer: Arc<Mutex<RecordingWriter>>,
+    /// Receives the audio before processing, when the session keeps a raw copy
+    raw_writer: Option<Arc<Mutex<RecordingWriter>>>,
+    dsp: Option<DspChain>,
+    /// Downmix/resample before writing, when the output format differs from the capture
+    converter: Option<OutputConverter>,
+    /// Feeds streaming transcription, when the session has it
+    chunker: Option<Chunker>,
+    /// Moves on to a new file every so often, when the session rotates files
+    rotator: Option<Rotator>,
+    /// Statistics of the processed audio, before any output conversion
+    quality: QualityMeter,
+    /// Capture frames written so far, which place markers in the file
+    frames: u64,
+    channels: u16,
+    sample_rate: u32,
+    output_rate: u32,
+}
+
+impl OutputPath {
+    /// Write one block to every file, stopping at the first write error
+    fn write(&mut self, data: &[f32]) -> io::Result<()> {
+        self.frames += (data.len() / self.channels.max(1) as usize) as u64;
+        if let Some(raw_writer) = &self.raw_writer {
+            if let Ok(mut w) = raw_writer.lock() {
+                w.write_samples_f32(data)?;
+            }
+        }
+
+        let data = match self.dsp.as_mut() {
+            Some(dsp) => dsp.process(data),
+            None => data,
+        };
+        if let Some(chunker) = self.chunker.as_mut() {
+            chunker.process(data);
+        }
+        self.quality.process(data);
+        write_converted(
+            &mut self.converter,
+            &self.writer,
+            self.rotator.as_mut(),
+            data,
+        )
+    }
+
+    /// Frames written to the output file so far. The DSP chain and converter
+    /// compensate their delay, so this only scales for a different output rate.
+    fn position(&self) -> u64 {
+        self.frames * self.output_rate as u64 / self.sample_rate.max(1) as u64
+    }
+
+    /// Write out what the DSP chain and converter still hold back and send the last
+    /// streaming chunk. Write errors are only logged: the file gets finalized next either way.
+    fn flush(&mut self) {
+        if let Some(dsp) = self.dsp.as_mut() {
+            let tail = dsp.flush();
+            if let Some(chunker) = self.chunker.as_mut() {
+                chunker.process(tail);
+            }
+            self.quality.process(tail);
+            let written = write_converted(
+                &mut self.converter,
+                &self.writer,
+                self.rotator.as_mut(),
+                tail,
+            );
+            if let Err(e) = written {
+                error!("Failed to write processed audio tail: {}", e);
+            }
+        }
+        if let Some(chunker) = self.chunker.as_mut() {
+            chunker.flush();
+        }
+
+        let Some(converter) = self.converter.as_mut() else {
+            return;
+        };
+        match converter.flush() {
+            Ok(tail) => {
+                if let Err(e) = write_file(&self.writer, self.rotator.as_mut(), tail) {
+                    error!("Failed to write converted audio tail: {}", e);
+                }
+            }
+            Err(e) => error!("Failed to flush output conversion: {}", e),
+        }
+    }
+}
+
+/// Write captured audio to the file, converting it to the output format first if needed
+fn write_converted(
+    converter: &mut Option<OutputConverter>,
+    writer: &Mutex<RecordingWriter>,
+    rotator: Option<&mut Rotator>,
+    data: &[f32],
+) -> io::Result<()> {
+    let data = match converter {
+        Some(converter) => match converter.process(data) {
+            Ok(converted) => converted,
+            Err(e) => {
+                // A conversion glitch loses this block but not the file
+                error!("Failed to convert audio for output: {}", e);
+                return Ok(());
+            }
+        },
+        None => data,
+    };
+    write_file(writer, rotator, data)
+}
+
+/// Write output-format audio to the current file, rotating to the next one as needed
+fn write_file(
+    writer: &Mutex<RecordingWriter>,
+    rotator: Option<&mut Rotator>,
+    data: &[f32],
+) -> io::Result<()> {
+    match (writer.lock(), rotator) {
+        (Ok(mut w), Some(rotator)) => rotator.write(&mut w, data),
+        (Ok(mut w), None) => w.write_samples_f32(data),
+        (Err(_), _) => Ok(()),
+    }
+}
+
+/// Writer thread loop: drains the ring buffer and applies recorder commands.
+/// Commands are applied only after draining, so every sample captured before a
+/// command was sent is handled with the state it was captured in.
+fn run_writer(
+    mut input: CaptureInput,
+    cmd_rx: mpsc::Receiver<RecorderCmd>,
+    mut sink: InputSink,
+    channels: u16,
+    overruns: Arc<OverrunCounters>,
+) {
+    let channels = channels.max(1) as usize;
+
+    loop {
+        match cmd_rx.recv_timeout(DRAIN_INTERVAL) {
+            Ok(cmd) => {
+                drain_ring(&mut input, &mut sink, channels);
+                match cmd {
+                    RecorderCmd::Start(reply_tx) => {
+                        overruns.reset();
+                        sink.start();
+                        info!("Recording started");
+                        let _ = reply_tx.send(()); // Confirm command processed
                     }
-                },
-                err_fn,
-                None,
-            )
-            .map_err(|e| format!("Failed to build F32 stream: {}", e))?,
-        SampleFormat::I16 => device
-            .build_input_stream(
-                config,
-                move |data: &[i16], _: &_| {
-                    if is_recording.load(Ordering::Relaxed) {
-                        if let Ok(mut w) = writer.lock() {
-                            let _ = w.write_samples_i16(data);
+                    RecorderCmd::Stop(reply_tx) => {
+                        // A recording that stopped on its own was flushed then
+                        if !sink.has_finished() {
+                            sink.stop();
                         }
+                        info!("Recording stopped");
+                        let _ = reply_tx.send(()); // Confirm command processed
                     }
-                },
-                err_fn,
-                None,
-            )
-            .map_err(|e| format!("Failed to build I16 stream: {}", e))?,
-        SampleFormat::U16 => device
-            .build_input_stream(
-                config,
-                move |data: &[u16], _: &_| {
-                    if is_recording.load(Ordering::Relaxed) {
-                        if let Ok(mut w) = writer.lock() {
-                            let _ = w.write_samples_u16(data);
+                    RecorderCmd::Pause(reply_tx) => {
+                        // The writer stays open so resume keeps appending to the same file
+                        sink.pause();
+                        info!("Recording paused");
+                        let _ = reply_tx.send(());
+                    }
+                    RecorderCmd::Resume(reply_tx) => {
+                        sink.resume();
+                        info!("Recording resumed");
+                        let _ = reply_tx.send(());
+                    }
+                    RecorderCmd::Marker(label, reply_tx) => {
+                        let _ = reply_tx.send(sink.add_marker(label));
+                    }
+                    RecorderCmd::SwitchInput(next) => {
+                        // The old stream is gone, so its ring was just drained for good
+                        input.flush(&mut sink);
+                        input = *next;
+                        info!("Reading audio from the replacement input stream");
+                    }
+                    RecorderCmd::Shutdown => {
+                        info!("Shutting down writer thread");
+                        break;
+                    }
+                }
+            }
+            Err(mpsc::RecvTimeoutError::Timeout) => drain_ring(&mut input, &mut sink, channels),
+            Err(mpsc::RecvTimeoutError::Disconnected) => {
+                drain_ring(&mut input, &mut sink, channels);
+                break;
+            }
+        }
+    }
+}
+
+/// Where the writer thread reads captured audio from. A device taken over on
+/// failover may run at another rate, which is resampled to the session's.
+struct CaptureInput {
+    consumer: Consumer<f32>,
+    resampler: Option<OutputConverter>,
+}
+
+impl CaptureInput {
+    /// Hand samples to the sink at the session's rate
+    fn feed(resampler: &mut Option<OutputConverter>, sink: &mut InputSink, data: &[f32]) {
+        match resampler {
+            Some(resampler) => match resampler.process(data) {
+                Ok(resampled) => sink.process(resampled),
+                Err(e) => error!("Failed to resample input audio: {}", e),
+            },
+            None => sink.process(data),
+        }
+    }
+
+    /// Push out what the resampler still holds, once the input is done
+    fn flush(&mut self, sink: &mut InputSink) {
+        if let Some(resampler) = self.resampler.as_mut() {
+            match resampler.flush() {
+                Ok(tail) => sink.process(tail),
+                Err(e) => error!("Failed to flush input resampling: {}", e),
+            }
+        }
+    }
+}
+
+/// Feed everything currently in the ring buffer to the sink
+fn drain_ring(input: &mut CaptureInput, sink: &mut InputSink, channels: usize) {
+    // Whole frames only; the callback never pushes a partial one
+    let available = input.consumer.slots() / channels * channels;
+    if available == 0 {
+        return;
+    }
+    if let Ok(chunk) = input.consumer.read_chunk(available) {
+        let (head, tail) = chunk.as_slices();
+        CaptureInput::feed(&mut input.resampler, sink, head);
+        CaptureInput::feed(&mut input.resampler, sink, tail);
+        chunk.commit_all();
+    }
+}
+
+/// How often the stream thread checks that the callback is still running
+const WATCHDOG_INTERVAL: Duration = Duration::from_millis(500);
+
+/// A stream that delivers no audio for this long is treated as disconnected.
+/// Some backends never report an error when a device vanishes.
+const STALL_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Failover attempts per session, so a flapping device can't loop forever
+const MAX_FAILOVERS: u32 = 3;
+
+/// Owns the input stream for a session and replaces it if the device goes away
+struct StreamSupervisor {
+    /// Session status to report failures to; `None` for a second source
+    status: Option<Arc<SessionStatus>>,
+    recording_id: String,
+    device_id: String,
+    sample_rate: u32,
+    channels: u16,
+    failover_to_default: bool,
+    callback: Arc<CallbackShared>,
+    /// Writer thread, which reads from a new ring buffer after failover; `None` for a second source
+    cmd_tx: Option<mpsc::Sender<RecorderCmd>>,
+    stream_tx: mpsc::Sender<StreamMsg>,
+    app_handle: AppHandle,
+}
+
+impl StreamSupervisor {
+    /// Stream thread body: runs until the session closes. Whether the stream
+    /// started is reported on `ready_tx`; a stream that never started ends the thread.
+    fn run(
+        mut self,
+        device: Device,
+        config: cpal::SupportedStreamConfig,
+        producer: Producer<f32>,
+        stream_rx: mpsc::Receiver<StreamMsg>,
+        ready_tx: mpsc::Sender<Result<()>>,
+    ) {
+        // Build the stream IN this thread (required for macOS)
+        let stream = self.start_stream(&device, &config.config(), config.sample_format(), producer);
+        let mut stream = match stream {
+            Ok(stream) => stream,
+            Err(e) => {
+                error!("{}", e);
+                let _ = ready_tx.send(Err(e));
+                return;
+            }
+        };
+        info!("Audio stream started successfully");
+        let _ = ready_tx.send(Ok(()));
+
+        let mut failovers = 0;
+        loop {
+            let Some(reason) = self.watch(&stream_rx) else {
+                info!("Shutting down audio worker");
+                return; // Stream automatically drops here
+            };
+            drop(stream);
+            warn!("Input device '{}' lost: {}", self.device_id, reason);
+
+            let mut event = DeviceDisconnected {
+                recording_id: self.recording_id.clone(),
+                device_id: self.device_id.clone(),
+                reason,
+                failover_device_id: None,
+                failover_error: None,
+            };
+
+            let replacement = if !self.failover_to_default {
+                None
+            } else if failovers >= MAX_FAILOVERS {
+                event.failover_error = Some("Too many failovers in this session".to_string());
+                None
+            } else {
+                failovers += 1;
+                match self.failover() {
+                    Ok((device_id, new_stream)) => {
+                        info!("Failed over to input device '{}'", device_id);
+                        event.failover_device_id = Some(device_id.clone());
+                        if let Some(status) = &self.status {
+                            status.set_device_id(device_id.clone());
                         }
+                        self.device_id = device_id;
+                        Some(new_stream)
                     }
-                },
-                err_fn,
-                None,
-            )
-            .map_err(|e| format!("Failed to build U16 stream: {}", e))?,
-        _ => return Err(format!("Unsupported sample format: {:?}", sample_format)),
+                    Err(e) => {
+                        error!("Failover to the default input device failed: {}", e);
+                        event.failover_error = Some(e.to_string());
+                        None
+                    }
+                }
+            };
+            let reason = event.reason.clone();
+            let _ = self.app_handle.emit("recorder-device-disconnected", event);
+
+            match replacement {
+                Some(new_stream) => stream = new_stream,
+                None => {
+                    if let Some(status) = &self.status {
+                        status.fail(format!(
+                            "Input device '{}' lost: {}",
+                            self.device_id, reason
+                        ));
+                    }
+                    // Nothing more to capture; the file stays open until the session closes
+                    wait_for_stop(&stream_rx);
+                    info!("Shutting down audio worker");
+                    return;
+                }
+            }
+        }
+    }
+
+    fn start_stream(
+        &self,
+        device: &Device,
+        config: &cpal::StreamConfig,
+        sample_format: SampleFormat,
+        producer: Producer<f32>,
+    ) -> Result<Stream> {
+        let stream = build_input_stream(
+            device,
+            config,
+            sample_format,
+            self.channels,
+            self.callback.clone(),
+            producer,
+            self.stream_tx.clone(),
+        )?;
+        stream.play()?;
+        Ok(stream)
+    }
+
+    /// Block until the session closes (`None`) or the device is lost (`Some(reason)`)
+    fn watch(&self, stream_rx: &mpsc::Receiver<StreamMsg>) -> Option<String> {
+        let mut last_heartbeat = self.callback.heartbeat.load(Ordering::Relaxed);
+        let mut last_progress = Instant::now();
+
+        loop {
+            match stream_rx.recv_timeout(WATCHDOG_INTERVAL) {
+                Ok(StreamMsg::Stop) | Err(mpsc::RecvTimeoutError::Disconnected) => return None,
+                Ok(StreamMsg::Error(cpal::StreamError::DeviceNotAvailable)) => {
+                    return Some("Device is no longer available".to_string());
+                }
+                Ok(StreamMsg::Error(e)) => {
+                    // Often transient; the watchdog catches it if audio actually stops
+                    warn!("Input stream reported an error: {}", e);
+                }
+                Err(mpsc::RecvTimeoutError::Timeout) => {}
+            }
+
+            let heartbeat = self.callback.heartbeat.load(Ordering::Relaxed);
+            if heartbeat != last_heartbeat {
+                last_heartbeat = heartbeat;
+                last_progress = Instant::now();
+            } else if last_progress.elapsed() >= STALL_TIMEOUT {
+                return Some(format!(
+                    "No audio received for {} seconds",
+                    STALL_TIMEOUT.as_secs()
+                ));
+            }
+        }
+    }
+
+    /// Open the default input device, feeding a new ring buffer that the writer
+    /// thread switches to once it has drained the old one. The session's sample
+    /// rate is preferred; at any other the writer thread resamples to it. The
+    /// channel count may differ too; the callback maps it onto the session's.
+    fn failover(&self) -> Result<(String, Stream)> {
+        let cmd_tx = self
+            .cmd_tx
+            .as_ref()
+            .ok_or_else(|| RecorderError::Internal {
+                message: "This input can't fail over".to_string(),
+            })?;
+        let host = cpal::default_host();
+        let (device_id, device) = device::default_input_device_with_id(&host).ok_or_else(|| {
+            RecorderError::DeviceNotFound {
+                message: "No default input device available".to_string(),
+            }
+        })?;
+
+        let min_channels = self
+            .callback
+            .routes
+            .iter()
+            .map(|route| route.channel as u16 + 1)
+            .max()
+            .unwrap_or(1);
+        let config = get_optimal_config(&device, Some(self.sample_rate), min_channels)?;
+        let device_rate = config.sample_rate().0;
+        let resampler = if device_rate != self.sample_rate {
+            info!(
+                "Device '{}' runs at {} Hz, resampling to the session's {} Hz",
+                device_id, device_rate, self.sample_rate
+            );
+            Some(OutputConverter::new(
+                device_rate,
+                self.channels,
+                self.sample_rate,
+                self.channels,
+            )?)
+        } else {
+            None
+        };
+
+        let (producer, consumer) = ring_buffer(device_rate, self.channels);
+        let stream =
+            self.start_stream(&device, &config.config(), config.sample_format(), producer)?;
+        cmd_tx
+            .send(RecorderCmd::SwitchInput(Box::new(CaptureInput {
+                consumer,
+                resampler,
+            })))
+            .map_err(|_| RecorderError::Internal {
+                message: "Writer thread is gone".to_string(),
+            })?;
+        Ok((device_id, stream))
+    }
+}
+
+/// Discard stream errors until the session closes
+fn wait_for_stop(stream_rx: &mpsc::Receiver<StreamMsg>) {
+    while let Ok(msg) = stream_rx.recv() {
+        if let StreamMsg::Stop = msg {
+            break;
+        }
+    }
+}
+
+/// Lock-free hand-off from the real-time callback to the writer thread.
+/// Two seconds of headroom absorbs slow or stalled disks.
+fn ring_buffer(sample_rate: u32, channels: u16) -> (Producer<f32>, Consumer<f32>) {
+    RingBuffer::new((sample_rate as usize * channels.max(1) as usize * 2).max(4096))
+}
+
+/// State shared with the real-time callback and the thread supervising it.
+/// The ring buffer producer isn't shared: each stream's callback owns its own.
+struct CallbackShared {
+    overruns: Arc<OverrunCounters>,
+    /// Bumped on every callback so a stalled device can be detected
+    heartbeat: AtomicU64,
+    /// Selected device channels; empty records every channel
+    routes: Vec<ChannelRoute>,
+}
+
+/// Push callback samples into the ring buffer, converting to f32 on the way.
+/// Frames are reduced to the selected channels, if any, and remapped from the
+/// device's channel count to the session's when they differ (after failover).
+/// Never blocks or allocates; if the writer falls behind the tail is dropped and counted.
+fn push_samples<T: Copy>(
+    shared: &CallbackShared,
+    producer: &mut Producer<f32>,
+    data: &[T],
+    device_channels: usize,
+    channels: usize,
+    convert: impl Fn(T) -> f32,
+) {
+    shared.heartbeat.fetch_add(1, Ordering::Relaxed);
+
+    let frames = data.len() / device_channels;
+    let writable_frames = (producer.slots() / channels).min(frames);
+    if writable_frames < frames {
+        shared.overruns.count.fetch_add(1, Ordering::Relaxed);
+        shared
+            .overruns
+            .dropped_samples
+            .fetch_add(((frames - writable_frames) * channels) as u64, Ordering::Relaxed);
+    }
+    if writable_frames == 0 {
+        return;
+    }
+    let Ok(chunk) = producer.write_chunk_uninit(writable_frames * channels) else {
+        return;
+    };
+
+    let routes = &shared.routes;
+    if routes.is_empty() && device_channels == channels {
+        chunk.fill_from_iter(data[..writable_frames * channels].iter().map(|&s| convert(s)));
+    } else if routes.is_empty() {
+        let frames = data.chunks_exact(device_channels).take(writable_frames);
+        chunk.fill_from_iter(frames.flat_map(|frame| {
+            let convert = &convert;
+            (0..channels).map(move |ch| {
+                if channels == 1 {
+                    // Mix everything down to mono
+                    frame.iter().map(|&s| convert(s)).sum::<f32>() / device_channels as f32
+                } else {
+                    convert(frame[ch % device_channels])
+                }
+            })
+        }));
+    } else {
+        // A replacement device may have fewer channels, so selections wrap around
+        let frames = data.chunks_exact(device_channels).take(writable_frames);
+        chunk.fill_from_iter(frames.flat_map(|frame| {
+            let convert = &convert;
+            let routed = move |route: &ChannelRoute| {
+                convert(frame[route.channel % device_channels]) * route.gain
+            };
+            (0..channels).map(move |ch| {
+                if channels == 1 {
+                    // Mix the selected channels down to mono
+                    routes.iter().map(routed).sum::<f32>() / routes.len() as f32
+                } else {
+                    routed(&routes[ch % routes.len()])
+                }
+            })
+        }));
+    }
+}
+
+/// Build input stream for any supported sample format.
+/// `channels` is the session's channel count, which the device's is mapped to.
+fn build_input_stream(
+    device: &Device,
+    config: &cpal::StreamConfig,
+    sample_format: SampleFormat,
+    channels: u16,
+    shared: Arc<CallbackShared>,
+    producer: Producer<f32>,
+    stream_tx: mpsc::Sender<StreamMsg>,
+) -> Result<Stream> {
+    match sample_format {
+        SampleFormat::I8 => {
+            build_typed_stream::<i8>(device, config, channels, shared, producer, stream_tx)
+        }
+        SampleFormat::I16 => {
+            build_typed_stream::<i16>(device, config, channels, shared, producer, stream_tx)
+        }
+        SampleFormat::I24 => {
+            build_typed_stream::<I24>(device, config, channels, shared, producer, stream_tx)
+        }
+        SampleFormat::I32 => {
+            build_typed_stream::<i32>(device, config, channels, shared, producer, stream_tx)
+        }
+        SampleFormat::I64 => {
+            build_typed_stream::<i64>(device, config, channels, shared, producer, stream_tx)
+        }
+        SampleFormat::U8 => {
+            build_typed_stream::<u8>(device, config, channels, shared, producer, stream_tx)
+        }
+        SampleFormat::U16 => {
+            build_typed_stream::<u16>(device, config, channels, shared, producer, stream_tx)
+        }
+        SampleFormat::U32 => {
+            build_typed_stream::<u32>(device, config, channels, shared, producer, stream_tx)
+        }
+        SampleFormat::U64 => {
+            build_typed_stream::<u64>(device, config, channels, shared, producer, stream_tx)
+        }
+        SampleFormat::F32 => {
+            build_typed_stream::<f32>(device, config, channels, shared, producer, stream_tx)
+        }
+        SampleFormat::F64 => {
+            build_typed_stream::<f64>(device, config, channels, shared, producer, stream_tx)
+        }
+        _ => Err(RecorderError::UnsupportedConfig {
+            message: format!("Unsupported sample format: {:?}", sample_format),
+        }),
+    }
+}
+
+/// Build an input stream whose callback converts `T` samples to f32.
+/// The callback takes ownership of the ring buffer producer.
+fn build_typed_stream<T>(
+    device: &Device,
+    config: &cpal::StreamConfig,
+    channels: u16,
+    shared: Arc<CallbackShared>,
+    mut producer: Producer<f32>,
+    stream_tx: mpsc::Sender<StreamMsg>,
+) -> Result<Stream>
+where
+    T: SizedSample,
+    f32: FromSample<T>,
+{
+    let err_fn = move |err| {
+        error!("Audio stream error: {}", err);
+        let _ = stream_tx.send(StreamMsg::Error(err));
     };
+    let device_channels = config.channels.max(1) as usize;
+    let channels = channels.max(1) as usize;
+
+    device
+        .build_input_stream(
+            config,
+            move |data: &[T], _: &_| {
+                push_samples(
+                    &shared,
+                    &mut producer,
+                    data,
+                    device_channels,
+                    channels,
+                    |s| s.to_sample::<f32>(),
+                )
+            },
+            err_fn,
+            None,
+        )
+        .map_err(|e| {
+            error!("Failed to build {} stream: {}", T::FORMAT, e);
+            RecorderError::from(e)
+        })
+}
 
-    Ok(stream)
+fn no_session() -> RecorderError {
+    RecorderError::NoSession {
+        message: "No recording session initialized".to_string(),
+    }
 }
 
 impl Drop for RecorderState {
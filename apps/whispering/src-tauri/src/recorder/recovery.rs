@@ -0,0 +1,598 @@
+use crate::recorder::dsp::RAW_FOLDER;
+use crate::recorder::error::RecorderError;
+use crate::recorder::rotation::SEGMENT_FOLDER;
+use crate::recorder::recorder::Result;
+use log::{debug, info, warn};
+use serde::Serialize;
+use std::fs::{File, OpenOptions};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+/// Placeholder `WavWriter` leaves in size fields until the first header update
+const PLACEHOLDER_SIZE: u32 = u32::MAX;
+
+/// Never read more than this much header looking for the data chunk
+const MAX_HEADER_SCAN: u64 = 64 * 1024;
+
+/// A WAV file whose header doesn't describe the audio actually on disk - returned to frontend
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RecoverableRecording {
+    pub recording_id: String,
+    pub file_path: String,
+    pub sample_rate: u32,
+    pub channels: u16,
+    /// Duration of the audio on disk, not what the stale header claims
+    pub duration_seconds: f32,
+    pub file_size_bytes: u64,
+    /// Last modification time, in milliseconds since the Unix epoch
+    pub modified_at_ms: Option<u64>,
+}
+
+/// Layout of a WAV/RF64 header as found on disk
+struct WavLayout {
+    is_rf64: bool,
+    /// Start of the ds64 chunk, or of a JUNK chunk big enough to become one
+    ds64_chunk_pos: Option<u64>,
+    sample_rate: u32,
+    channels: u16,
+    block_align: u16,
+    data_chunk_size_pos: u64,
+    declared_data_size: u64,
+    file_len: u64,
+}
+
+impl WavLayout {
+    fn data_start(&self) -> u64 {
+        self.data_chunk_size_pos + 4
+    }
+
+    /// Audio bytes actually present, trimmed to whole frames
+    fn actual_data_size(&self) -> u64 {
+        let available = self.file_len.saturating_sub(self.data_start());
+        available - available % self.block_align.max(1) as u64
+    }
+
+    fn duration_seconds(&self, data_size: u64) -> f32 {
+        let bytes_per_second = self.sample_rate as u64 * self.block_align as u64;
+        if bytes_per_second == 0 {
+            0.0
+        } else {
+            data_size as f32 / bytes_per_second as f32
+        }
+    }
+}
+
+/// Scan a recordings folder, and its subfolders of raw takes and segments, for
+/// WAV files left unfinalized by a crash. Files belonging to open sessions (`active_files`) are skipped.
+pub fn list_recoverable_recordings(
+    folder: &Path,
+    active_files: &[PathBuf],
+) -> Result<Vec<RecoverableRecording>> {
+    let entries = std::fs::read_dir(folder).map_err(|e| RecorderError::FileRead {
+        message: format!("Failed to read recordings folder {:?}: {}", folder, e),
+    })?;
+    let subfolder_entries = [RAW_FOLDER, SEGMENT_FOLDER]
+        .into_iter()
+        .filter_map(|name| std::fs::read_dir(folder.join(name)).ok())
+        .flatten();
+
+    // Each active session's segment files share its file stem as a prefix
+    let active_stems: Vec<&str> = active_files
+        .iter()
+        .filter_map(|path| path.file_stem())
+        .filter_map(|stem| stem.to_str())
+        .collect();
+
+    let mut recordings = Vec::new();
+    for entry in entries.chain(subfolder_entries).flatten() {
+        let path = entry.path();
+        let is_wav = path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("wav"));
+        if !is_wav || !path.is_file() {
+            continue;
+        }
+
+        let stem = path.file_stem().and_then(|s| s.to_str()).unwrap_or("");
+        if active_stems.iter().any(|active| stem.starts_with(active)) {
+            continue;
+        }
+
+        match inspect_wav(&path) {
+            Ok(Some(layout)) => recordings.push(describe(&path, &layout)),
+            Ok(None) => {}
+            Err(e) => debug!("Skipping {:?} during recovery scan: {}", path, e),
+        }
+    }
+
+    // Most recent first
+    recordings.sort_by_key(|r| std::cmp::Reverse(r.modified_at_ms));
+
+    if !recordings.is_empty() {
+        info!(
+            "Found {} recoverable recording(s) in {:?}",
+            recordings.len(),
+            folder
+        );
+    }
+
+    Ok(recordings)
+}
+
+/// Repair the header of an unfinalized WAV file from its actual length.
+///
+/// A trailing partial frame is truncated away. Files that grew past 4 GiB
+/// are promoted to RF64 using the chunk `WavWriter` reserves for that.
+pub fn recover_recording(path: &Path) -> Result<RecoverableRecording> {
+    let layout = inspect_wav(path)?.ok_or_else(|| RecorderError::InvalidState {
+        message: format!("Recording {:?} is already finalized", path),
+    })?;
+
+    let data_size = layout.actual_data_size();
+    // Everything after the RIFF size field: header chunks plus sample data
+    let riff_size = layout.data_start() - 8 + data_size;
+
+    let mut file = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open(path)
+        .map_err(|e| RecorderError::disk_write(e, format!("Failed to open {:?}", path)))?;
+
+    let write_result = (|| -> std::io::Result<()> {
+        file.set_len(layout.data_start() + data_size)?;
+
+        if layout.is_rf64 || riff_size > u32::MAX as u64 {
+            let Some(ds64_pos) = layout.ds64_chunk_pos else {
+                return Err(std::io::Error::other(
+                    "file exceeds 4 GiB and has no room for a ds64 chunk",
+                ));
+            };
+            let sample_count = data_size / layout.block_align.max(1) as u64;
+
+            file.seek(SeekFrom::Start(0))?;
+            file.write_all(b"RF64")?;
+            file.write_all(&PLACEHOLDER_SIZE.to_le_bytes())?;
+            file.seek(SeekFrom::Start(ds64_pos))?;
+            file.write_all(b"ds64")?;
+            file.seek(SeekFrom::Start(ds64_pos + 8))?;
+            file.write_all(&riff_size.to_le_bytes())?;
+            file.write_all(&data_size.to_le_bytes())?;
+            file.write_all(&sample_count.to_le_bytes())?;
+            file.seek(SeekFrom::Start(layout.data_chunk_size_pos))?;
+            file.write_all(&PLACEHOLDER_SIZE.to_le_bytes())?;
+        } else {
+            file.seek(SeekFrom::Start(4))?;
+            file.write_all(&(riff_size as u32).to_le_bytes())?;
+            file.seek(SeekFrom::Start(layout.data_chunk_size_pos))?;
+            file.write_all(&(data_size as u32).to_le_bytes())?;
+        }
+
+        file.sync_all()
+    })();
+    write_result
+        .map_err(|e| RecorderError::disk_write(e, format!("Failed to repair {:?}", path)))?;
+
+    let repaired = WavLayout {
+        declared_data_size: data_size,
+        file_len: layout.data_start() + data_size,
+        ..layout
+    };
+    info!(
+        "Recovered recording {:?}: {:.2} seconds",
+        path,
+        repaired.duration_seconds(data_size)
+    );
+
+    Ok(describe(path, &repaired))
+}
+
+/// Parse the header and return its layout if the file needs repair
+fn inspect_wav(path: &Path) -> Result<Option<WavLayout>> {
+    let read_error = |e: &dyn std::fmt::Display| RecorderError::FileRead {
+        message: format!("Failed to read {:?}: {}", path, e),
+    };
+    let mut file = File::open(path).map_err(|e| read_error(&e))?;
+    let file_len = file.metadata().map_err(|e| read_error(&e))?.len();
+
+    let mut header = Vec::new();
+    (&mut file)
+        .take(MAX_HEADER_SCAN)
+        .read_to_end(&mut header)
+        .map_err(|e| read_error(&e))?;
+
+    let layout = parse_header(&header, file_len).map_err(|e| read_error(&e))?;
+
+    let declared_end = layout.data_start() + layout.declared_data_size;
+    let needs_repair = if declared_end > file_len {
+        // Placeholder sizes, or a header claiming more than was written
+        true
+    } else if declared_end + (layout.declared_data_size & 1) >= file_len {
+        false
+    } else {
+        // Trailing bytes are fine if they're another chunk (e.g. LIST after data),
+        // but raw audio beyond the declared size means headers went stale
+        let mut next_id = [0u8; 4];
+        let padded_end = declared_end + (layout.declared_data_size & 1);
+        file.seek(SeekFrom::Start(padded_end))
+            .and_then(|_| file.read_exact(&mut next_id))
+            .map(|_| !is_chunk_id(&next_id))
+            .unwrap_or(true)
+    };
+
+    if needs_repair && layout.actual_data_size() == 0 {
+        warn!("Unfinalized recording {:?} contains no audio", path);
+    }
+
+    Ok(needs_repair.then_some(layout))
+}
+
+fn parse_header(header: &[u8], file_len: u64) -> std::result::Result<WavLayout, &'static str> {
+    if header.len() < 12 || &header[8..12] != b"WAVE" {
+        return Err("Not a WAV file");
+    }
+    let is_rf64 = match &header[0..4] {
+        b"RIFF" => false,
+        b"RF64" => true,
+        _ => return Err("Not a WAV file"),
+    };
+
+    let mut ds64_chunk_pos = None;
+    let mut ds64_data_size = None;
+    let mut format = None;
+    let mut pos = 12usize;
+
+    while pos + 8 <= header.len() {
+        let id = &header[pos..pos + 4];
+        let size = read_u32(header, pos + 4);
+        let body = pos + 8;
+
+        match id {
+            b"ds64" if body + 16 <= header.len() => {
+                ds64_chunk_pos = Some(pos as u64);
+                ds64_data_size = Some(read_u64(header, body + 8));
+            }
+            b"JUNK" if size >= 28 => {
+                ds64_chunk_pos.get_or_insert(pos as u64);
+            }
+            b"fmt " if body + 16 <= header.len() => {
+                format = Some((
+                    read_u16(header, body + 2),  // Channels
+                    read_u32(header, body + 4),  // Sample rate
+                    read_u16(header, body + 12), // Block align
+                ));
+            }
+            b"data" => {
+                let (channels, sample_rate, block_align) =
+                    format.ok_or("data chunk before fmt chunk")?;
+                let declared_data_size = if is_rf64 && size == PLACEHOLDER_SIZE {
+                    ds64_data_size.ok_or("RF64 file without a ds64 chunk")?
+                } else {
+                    size as u64
+                };
+                return Ok(WavLayout {
+                    is_rf64,
+                    ds64_chunk_pos,
+                    sample_rate,
+                    channels,
+                    block_align,
+                    data_chunk_size_pos: pos as u64 + 4,
+                    declared_data_size,
+                    file_len,
+                });
+            }
+            _ => {}
+        }
+
+        // Chunks are padded to an even size
+        pos = body + size as usize + (size & 1) as usize;
+    }
+
+    Err("No data chunk found")
+}
+
+fn describe(path: &Path, layout: &WavLayout) -> RecoverableRecording {
+    let data_size = layout.actual_data_size();
+    let modified_at_ms = std::fs::metadata(path)
+        .and_then(|m| m.modified())
+        .ok()
+        .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+        .map(|d| d.as_millis() as u64);
+
+    RecoverableRecording {
+        recording_id: path
+            .file_stem()
+            .and_then(|s| s.to_str())
+            .unwrap_or_default()
+            .to_string(),
+        file_path: path.to_string_lossy().to_string(),
+        sample_rate: layout.sample_rate,
+        channels: layout.channels,
+        duration_seconds: layout.duration_seconds(data_size),
+        file_size_bytes: layout.file_len,
+        modified_at_ms,
+    }
+}
+
+fn is_chunk_id(id: &[u8; 4]) -> bool {
+    id.iter().all(|&b| b.is_ascii_alphanumeric() || b == b' ')
+}
+
+fn read_u16(data: &[u8], pos: usize) -> u16 {
+    u16::from_le_bytes([data[pos], data[pos + 1]])
+}
+
+fn read_u32(data: &[u8], pos: usize) -> u32 {
+    u32::from_le_bytes([data[pos], data[pos + 1], data[pos + 2], data[pos + 3]])
+}
+
+fn read_u64(data: &[u8], pos: usize) -> u64 {
+    let mut bytes = [0u8; 8];
+    bytes.copy_from_slice(&data[pos..pos + 8]);
+    u64::from_le_bytes(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE_RATE: u32 = 16000;
+    /// 16-bit stereo
+    const BLOCK_ALIGN: u16 = 4;
+    /// Bytes before the sample data, as `WavWriter` lays them out
+    const DATA_START: u64 = 80;
+
+    /// Header as `WavWriter` writes it: RIFF/RF64, JUNK or ds64, fmt, data
+    fn header(magic: &[u8; 4], riff_size: u32, ds64: Option<[u64; 3]>, data_size: u32) -> Vec<u8> {
+        let mut header = Vec::new();
+        header.extend_from_slice(magic);
+        header.extend_from_slice(&riff_size.to_le_bytes());
+        header.extend_from_slice(b"WAVE");
+        match ds64 {
+            Some(sizes) => {
+                header.extend_from_slice(b"ds64");
+                header.extend_from_slice(&28u32.to_le_bytes());
+                for size in sizes {
+                    header.extend_from_slice(&size.to_le_bytes());
+                }
+                header.extend_from_slice(&0u32.to_le_bytes());
+            }
+            None => {
+                header.extend_from_slice(b"JUNK");
+                header.extend_from_slice(&28u32.to_le_bytes());
+                header.extend_from_slice(&[0; 28]);
+            }
+        }
+        header.extend_from_slice(b"fmt ");
+        header.extend_from_slice(&16u32.to_le_bytes());
+        header.extend_from_slice(&1u16.to_le_bytes());
+        header.extend_from_slice(&2u16.to_le_bytes());
+        header.extend_from_slice(&SAMPLE_RATE.to_le_bytes());
+        header.extend_from_slice(&(SAMPLE_RATE * BLOCK_ALIGN as u32).to_le_bytes());
+        header.extend_from_slice(&BLOCK_ALIGN.to_le_bytes());
+        header.extend_from_slice(&16u16.to_le_bytes());
+        header.extend_from_slice(b"data");
+        header.extend_from_slice(&data_size.to_le_bytes());
+        assert_eq!(header.len() as u64, DATA_START);
+        header
+    }
+
+    /// Stand-in for recorded audio; deliberately not printable ASCII
+    fn audio(len: usize) -> Vec<u8> {
+        (0..len).map(|i| 0x80 | (i % 0x7f) as u8).collect()
+    }
+
+    fn write(dir: &Path, name: &str, parts: &[&[u8]]) -> PathBuf {
+        let path = dir.join(name);
+        std::fs::write(&path, parts.concat()).unwrap();
+        path
+    }
+
+    /// Header as it reads back after a repair
+    fn reread(path: &Path) -> (Vec<u8>, WavLayout) {
+        let bytes = std::fs::read(path).unwrap();
+        let layout = parse_header(&bytes, bytes.len() as u64).unwrap();
+        (bytes, layout)
+    }
+
+    fn assert_recovered(path: &Path, data_size: u64) {
+        let recording = recover_recording(path).unwrap();
+        assert_eq!(recording.sample_rate, SAMPLE_RATE);
+        assert_eq!(recording.channels, 2);
+        assert_eq!(
+            recording.duration_seconds,
+            data_size as f32 / (SAMPLE_RATE * BLOCK_ALIGN as u32) as f32
+        );
+
+        let (bytes, layout) = reread(path);
+        assert_eq!(bytes.len() as u64, DATA_START + data_size);
+        assert_eq!(layout.declared_data_size, data_size);
+        assert_eq!(read_u32(&bytes, 4) as u64, DATA_START - 8 + data_size);
+        assert!(inspect_wav(path).unwrap().is_none());
+    }
+
+    #[test]
+    fn repairs_placeholder_sizes() {
+        let dir = tempfile::tempdir().unwrap();
+        // Crashed before the first header update, mid-frame
+        let header = header(b"RIFF", PLACEHOLDER_SIZE, None, PLACEHOLDER_SIZE);
+        let path = write(dir.path(), "crashed.wav", &[&header, &audio(4001)]);
+
+        let found = list_recoverable_recordings(dir.path(), &[]).unwrap();
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].recording_id, "crashed");
+        assert_eq!(found[0].duration_seconds, 4000.0 / 64000.0);
+
+        assert_recovered(&path, 4000);
+    }
+
+    #[test]
+    fn repairs_truncated_riff() {
+        let dir = tempfile::tempdir().unwrap();
+        // Header claims more audio than made it to disk
+        let header = header(b"RIFF", 72 + 8000, None, 8000);
+        let path = write(dir.path(), "truncated.wav", &[&header, &audio(2002)]);
+        assert_recovered(&path, 2000);
+    }
+
+    #[test]
+    fn repairs_stale_sizes() {
+        let dir = tempfile::tempdir().unwrap();
+        // Audio kept coming after the last header update, including digital silence
+        let header = header(b"RIFF", 72 + 400, None, 400);
+        let path = write(dir.path(), "stale.wav", &[&header, &audio(400), &[0; 1600]]);
+        assert_recovered(&path, 2000);
+    }
+
+    #[test]
+    fn repairs_truncated_rf64() {
+        let dir = tempfile::tempdir().unwrap();
+        let ds64 = [72 + 8000, 8000, 2000];
+        let header = header(b"RF64", PLACEHOLDER_SIZE, Some(ds64), PLACEHOLDER_SIZE);
+        let path = write(dir.path(), "big.wav", &[&header, &audio(2003)]);
+        assert_recovered_rf64(&path, 2000);
+    }
+
+    #[test]
+    fn repairs_stale_rf64() {
+        let dir = tempfile::tempdir().unwrap();
+        let ds64 = [72 + 400, 400, 100];
+        let header = header(b"RF64", PLACEHOLDER_SIZE, Some(ds64), PLACEHOLDER_SIZE);
+        let path = write(dir.path(), "big.wav", &[&header, &audio(2000)]);
+        assert_recovered_rf64(&path, 2000);
+    }
+
+    fn assert_recovered_rf64(path: &Path, data_size: u64) {
+        recover_recording(path).unwrap();
+
+        let (bytes, layout) = reread(path);
+        assert_eq!(&bytes[0..4], b"RF64");
+        assert_eq!(read_u32(&bytes, 4), PLACEHOLDER_SIZE);
+        assert_eq!(&bytes[12..16], b"ds64");
+        assert_eq!(read_u64(&bytes, 20), DATA_START - 8 + data_size);
+        assert_eq!(read_u64(&bytes, 28), data_size);
+        assert_eq!(read_u64(&bytes, 36), data_size / BLOCK_ALIGN as u64);
+        assert_eq!(read_u32(&bytes, DATA_START as usize - 4), PLACEHOLDER_SIZE);
+        assert_eq!(layout.declared_data_size, data_size);
+        assert_eq!(bytes.len() as u64, DATA_START + data_size);
+        assert!(inspect_wav(path).unwrap().is_none());
+    }
+
+    #[test]
+    fn promotes_oversized_riff_to_rf64() {
+        let dir = tempfile::tempdir().unwrap();
+        let header = header(b"RIFF", PLACEHOLDER_SIZE, None, PLACEHOLDER_SIZE);
+        let path = write(dir.path(), "huge.wav", &[&header]);
+        // Sparse, so the test doesn't need 4 GiB of disk
+        let data_size = u32::MAX as u64 + 1 + BLOCK_ALIGN as u64;
+        File::options()
+            .write(true)
+            .open(&path)
+            .unwrap()
+            .set_len(DATA_START + data_size + 1)
+            .unwrap();
+
+        recover_recording(&path).unwrap();
+
+        let mut header = vec![0; DATA_START as usize];
+        File::open(&path).unwrap().read_exact(&mut header).unwrap();
+        assert_eq!(&header[0..4], b"RF64");
+        assert_eq!(&header[12..16], b"ds64");
+        assert_eq!(read_u64(&header, 28), data_size);
+        assert_eq!(read_u64(&header, 36), data_size / BLOCK_ALIGN as u64);
+        assert_eq!(
+            std::fs::metadata(&path).unwrap().len(),
+            DATA_START + data_size
+        );
+    }
+
+    #[test]
+    fn finalized_files_are_left_alone() {
+        let dir = tempfile::tempdir().unwrap();
+        let header = header(b"RIFF", 72 + 2000, None, 2000);
+        let path = write(dir.path(), "done.wav", &[&header, &audio(2000)]);
+
+        assert!(list_recoverable_recordings(dir.path(), &[])
+            .unwrap()
+            .is_empty());
+        assert!(matches!(
+            recover_recording(&path),
+            Err(RecorderError::InvalidState { .. })
+        ));
+    }
+
+    #[test]
+    fn chunks_after_data_are_not_stale_audio() {
+        let dir = tempfile::tempdir().unwrap();
+        let header = header(b"RIFF", 72 + 2000 + 20, None, 2000);
+        let mut list = b"LIST".to_vec();
+        list.extend_from_slice(&12u32.to_le_bytes());
+        list.extend_from_slice(b"INFOICMT\0\0\0\0");
+        write(dir.path(), "tagged.wav", &[&header, &audio(2000), &list]);
+
+        let mut cue = b"cue ".to_vec();
+        cue.extend_from_slice(&4u32.to_le_bytes());
+        cue.extend_from_slice(&0u32.to_le_bytes());
+        write(dir.path(), "marked.wav", &[&header, &audio(2000), &cue]);
+
+        assert!(list_recoverable_recordings(dir.path(), &[])
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn is_chunk_id_accepts_only_printable_ids() {
+        assert!(is_chunk_id(b"LIST"));
+        assert!(is_chunk_id(b"cue "));
+        assert!(is_chunk_id(b"id3 "));
+        assert!(!is_chunk_id(&[0, 0, 0, 0]));
+        assert!(!is_chunk_id(&[0x12, 0xfe, 0x80, 0x7f]));
+        assert!(!is_chunk_id(b"LI\0T"));
+    }
+
+    #[test]
+    fn skips_active_sessions_and_other_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let header = header(b"RIFF", PLACEHOLDER_SIZE, None, PLACEHOLDER_SIZE);
+        write(dir.path(), "old.wav", &[&header, &audio(400)]);
+        let live = write(dir.path(), "live.wav", &[&header, &audio(400)]);
+        write(dir.path(), "notes.txt", &[&header, &audio(400)]);
+        write(dir.path(), "garbage.wav", &[b"not a wav file at all"]);
+
+        let found = list_recoverable_recordings(dir.path(), &[live]).unwrap();
+        let ids: Vec<&str> = found.iter().map(|r| r.recording_id.as_str()).collect();
+        assert_eq!(ids, ["old"]);
+    }
+
+    #[test]
+    fn finds_later_segments() {
+        let dir = tempfile::tempdir().unwrap();
+        let header = header(b"RIFF", PLACEHOLDER_SIZE, None, PLACEHOLDER_SIZE);
+        let segment_folder = dir.path().join(SEGMENT_FOLDER);
+        std::fs::create_dir(&segment_folder).unwrap();
+        let old = write(&segment_folder, "old.002.wav", &[&header, &audio(400)]);
+        let live = write(dir.path(), "live.wav", &[&header, &audio(400)]);
+        write(&segment_folder, "live.002.wav", &[&header, &audio(400)]);
+
+        let found = list_recoverable_recordings(dir.path(), &[live]).unwrap();
+        let paths: Vec<&str> = found.iter().map(|r| r.file_path.as_str()).collect();
+        assert_eq!(paths, [old.to_string_lossy()]);
+    }
+
+    #[test]
+    fn finds_raw_takes() {
+        let dir = tempfile::tempdir().unwrap();
+        let header = header(b"RIFF", PLACEHOLDER_SIZE, None, PLACEHOLDER_SIZE);
+        let raw_folder = dir.path().join(RAW_FOLDER);
+        std::fs::create_dir(&raw_folder).unwrap();
+        let raw = write(&raw_folder, "old.wav", &[&header, &audio(400)]);
+        let live = write(dir.path(), "live.wav", &[&header, &audio(400)]);
+        write(&raw_folder, "live.wav", &[&header, &audio(400)]);
+
+        let found = list_recoverable_recordings(dir.path(), &[live]).unwrap();
+        let paths: Vec<&str> = found.iter().map(|r| r.file_path.as_str()).collect();
+        assert_eq!(paths, [raw.to_string_lossy()]);
+    }
+}
@@ -0,0 +1,197 @@
+use crate::recorder::encoder::RecordingWriter;
+use crate::recorder::wav_writer::WavSampleFormat;
+use log::info;
+use serde::{Deserialize, Serialize};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use tauri::{AppHandle, Emitter};
+
+/// Segments are never cut shorter than this, whatever the limits say
+const MIN_SEGMENT_SECONDS: f32 = 1.0;
+
+/// Subfolder of the recordings folder holding every segment after the first.
+/// Kept out of the top level, where the app database takes any
+/// `{recording_id}.*` file for the whole recording.
+pub const SEGMENT_FOLDER: &str = "segments";
+
+/// When to move on to a new file during a long recording - sent from frontend.
+/// Whichever limit is reached first starts the next segment.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RotationConfig {
+    /// Audio per file, in minutes
+    pub max_minutes: Option<f32>,
+    /// Approximate file size, in megabytes (1,000,000 bytes)
+    pub max_megabytes: Option<f32>,
+}
+
+/// One file of a recording, in recording order - returned to frontend
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RecordingSegment {
+    /// 1-based
+    pub index: u32,
+    pub file_path: String,
+    pub duration_seconds: f32,
+}
+
+/// Payload for the `recorder-segment-closed` event - the file is finalized and
+/// can be transcribed while the recording goes on
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SegmentClosed {
+    recording_id: String,
+    segment: RecordingSegment,
+}
+
+/// Path of segment `index`: the first keeps the session's file name, later ones
+/// are numbered before the extension in the segment folder
+/// (`segments/{recording_id}.002.wav`)
+pub fn segment_path(first: &Path, index: u32) -> PathBuf {
+    if index <= 1 {
+        return first.to_path_buf();
+    }
+    let extension = first
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .unwrap_or_default();
+    let numbered = first.with_extension(format!("{:03}.{}", index, extension));
+    first
+        .parent()
+        .unwrap_or_else(|| Path::new(""))
+        .join(SEGMENT_FOLDER)
+        .join(numbered.file_name().unwrap_or_default())
+}
+
+/// Splits the output file into numbered segments on the writer thread.
+///
+/// Blocks are cut at the exact frame where a segment fills up, so every sample
+/// lands in exactly one file. The next file is only opened once audio for it
+/// arrives, so stopping right at a boundary never leaves an empty segment.
+pub struct Rotator {
+    frames_per_segment: u64,
+    frames_in_segment: u64,
+    /// Output frames in the segments already closed
+    segment_start: u64,
+    /// Segment currently being written, 1-based
+    index: u32,
+    channels: usize,
+    first_path: PathBuf,
+    sample_format: WavSampleFormat,
+    /// Closed segments, shared with the session so `stop_recording` can list them
+    closed: Arc<Mutex<Vec<RecordingSegment>>>,
+    recording_id: String,
+    app_handle: AppHandle,
+}
+
+impl Rotator {
+    /// Rotate the files of `writer` per `config`; `None` if no limit is set
+    pub fn new(
+        config: &RotationConfig,
+        writer: &RecordingWriter,
+        sample_format: WavSampleFormat,
+        closed: Arc<Mutex<Vec<RecordingSegment>>>,
+        recording_id: String,
+        app_handle: AppHandle,
+    ) -> Option<Self> {
+        let (sample_rate, channels) = writer.input_format();
+        let bytes_per_second = writer
+            .format()
+            .bytes_per_second(sample_rate, channels, sample_format)
+            .max(1);
+        let seconds = [
+            config.max_minutes.map(|minutes| minutes * 60.0),
+            config
+                .max_megabytes
+                .map(|megabytes| megabytes * 1_000_000.0 / bytes_per_second as f32),
+        ]
+        .into_iter()
+        .flatten()
+        .reduce(f32::min)?
+        .max(MIN_SEGMENT_SECONDS);
+
+        info!(
+            "Rotating recording files every {:.1} minutes of audio",
+            seconds / 60.0
+        );
+        Some(Self {
+            frames_per_segment: (seconds as f64 * sample_rate as f64) as u64,
+            frames_in_segment: 0,
+            segment_start: 0,
+            index: 1,
+            channels: channels.max(1) as usize,
+            first_path: writer.get_file_path().clone(),
+            sample_format,
+            closed,
+            recording_id,
+            app_handle,
+        })
+    }
+
+    /// Output frames written before the current segment began
+    pub fn segment_start(&self) -> u64 {
+        self.segment_start
+    }
+
+    /// Write interleaved samples, moving on to the next file whenever one fills up
+    pub fn write(&mut self, writer: &mut RecordingWriter, mut data: &[f32]) -> io::Result<()> {
+        while !data.is_empty() {
+            if self.frames_in_segment >= self.frames_per_segment {
+                self.rotate(writer)?;
+            }
+            let room = (self.frames_per_segment - self.frames_in_segment) as usize * self.channels;
+            let (now, rest) = data.split_at(room.min(data.len()));
+            writer.write_samples_f32(now)?;
+            self.frames_in_segment += (now.len() / self.channels) as u64;
+            data = rest;
+        }
+        Ok(())
+    }
+
+    /// Finalize the current file and continue in a new one. The next file is
+    /// created first, so a failure leaves the current one open for the caller.
+    fn rotate(&mut self, writer: &mut RecordingWriter) -> io::Result<()> {
+        let (sample_rate, channels) = writer.input_format();
+        let next_path = segment_path(&self.first_path, self.index + 1);
+        if let Some(folder) = next_path.parent() {
+            std::fs::create_dir_all(folder)?;
+        }
+        let mut next = RecordingWriter::new(
+            writer.format(),
+            next_path,
+            sample_rate,
+            channels,
+            self.sample_format,
+        )?;
+        next.set_title(format!("{} (part {})", self.recording_id, self.index + 1));
+
+        let mut finished = std::mem::replace(writer, next);
+        let result = finished.finalize();
+        let segment = RecordingSegment {
+            index: self.index,
+            file_path: finished.get_file_path().to_string_lossy().to_string(),
+            duration_seconds: finished.get_duration_seconds(),
+        };
+        if let Ok(mut closed) = self.closed.lock() {
+            closed.push(segment.clone());
+        }
+        self.index += 1;
+        self.segment_start += self.frames_in_segment;
+        self.frames_in_segment = 0;
+        result?;
+
+        info!(
+            "Closed segment {} ({:.2}s): {}",
+            segment.index, segment.duration_seconds, segment.file_path
+        );
+        let _ = self.app_handle.emit(
+            "recorder-segment-closed",
+            SegmentClosed {
+                recording_id: self.recording_id.clone(),
+                segment,
+            },
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,283 @@
+use crate::recorder::error::RecorderError;
+use crate::recorder::recorder::Result;
+use log::{error, warn};
+use rtrb::Consumer;
+use rubato::{
+    calculate_cutoff, Resampler, SincFixedIn, SincInterpolationParameters, SincInterpolationType,
+    WindowFunction,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+
+/// How a second input source is combined with the main one
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SourceLayout {
+    /// Both sources summed into a single mono track
+    #[default]
+    Mixed,
+    /// Two channels: main input on the left, second source on the right
+    Split,
+}
+
+impl SourceLayout {
+    pub fn channels(&self) -> u16 {
+        match self {
+            SourceLayout::Mixed => 1,
+            SourceLayout::Split => 2,
+        }
+    }
+}
+
+/// Second-source audio the mixer aims to keep waiting for the main input,
+/// enough to ride out the two devices delivering their callbacks at different times
+const TARGET_PENDING_MS: u32 = 50;
+
+/// Most second-source audio kept waiting. Drift correction holds the backlog
+/// near the target, so this only comes into play when a device stalls or
+/// jumps; anything beyond it is dropped.
+const MAX_PENDING_MS: u32 = 200;
+
+/// Largest change to the second source's rate, either way. Real clocks differ
+/// by well under 0.1%; the headroom lets the backlog settle quickly.
+const MAX_DRIFT_CORRECTION: f64 = 0.005;
+
+/// How far the backlog estimate moves toward each new measurement
+const BACKLOG_SMOOTHING: f64 = 0.02;
+
+/// Second-source frames per resampler call
+const RESAMPLER_CHUNK: usize = 256;
+
+/// Resamples the second source to the main input's rate at a ratio that can
+/// be nudged while running, to follow the drift between the two clocks
+struct DriftResampler {
+    resampler: SincFixedIn<f32>,
+    input: Vec<f32>,
+}
+
+impl DriftResampler {
+    fn new(source_rate: u32, sample_rate: u32) -> Result<Self> {
+        let parameters = SincInterpolationParameters {
+            sinc_len: 128,
+            f_cutoff: calculate_cutoff(128, WindowFunction::BlackmanHarris2),
+            oversampling_factor: 128,
+            interpolation: SincInterpolationType::Linear,
+            window: WindowFunction::BlackmanHarris2,
+        };
+        let resampler = SincFixedIn::<f32>::new(
+            sample_rate as f64 / source_rate as f64,
+            1.0 + MAX_DRIFT_CORRECTION,
+            parameters,
+            RESAMPLER_CHUNK,
+            1,
+        )
+        .map_err(|e| RecorderError::Processing {
+            message: format!("Failed to create resampler: {}", e),
+        })?;
+
+        Ok(Self {
+            resampler,
+            input: Vec::with_capacity(RESAMPLER_CHUNK),
+        })
+    }
+
+    fn process(&mut self, data: &[f32], output: &mut VecDeque<f32>) -> Result<()> {
+        for &sample in data {
+            self.input.push(sample);
+            if self.input.len() == RESAMPLER_CHUNK {
+                let resampled = self.resampler.process(&[&self.input], None).map_err(|e| {
+                    RecorderError::Processing {
+                        message: format!("Resampling failed: {}", e),
+                    }
+                })?;
+                output.extend(&resampled[0]);
+                self.input.clear();
+            }
+        }
+        Ok(())
+    }
+
+    /// Produce `1 + correction` times as many samples as the nominal ratio gives
+    fn set_correction(&mut self, correction: f64) {
+        if let Err(e) = self
+            .resampler
+            .set_resample_ratio_relative(1.0 + correction, true)
+        {
+            error!("Failed to adjust second source rate: {}", e);
+        }
+    }
+}
+
+/// Combines the main input with a second mono source (e.g. a PulseAudio/PipeWire
+/// monitor) on the writer thread.
+///
+/// The main input drives the timeline: every main frame is paired with the
+/// oldest pending second-source frame. The two devices run on separate clocks,
+/// so the second source is resampled at a rate that keeps its backlog steady
+/// instead of letting it drift ahead or behind.
+pub struct SourceMixer {
+    layout: SourceLayout,
+    consumer: Consumer<f32>,
+    resampler: DriftResampler,
+    pending: VecDeque<f32>,
+    target_pending: usize,
+    max_pending: usize,
+    /// Smoothed backlog, in samples
+    backlog: f64,
+    /// The second source joins once its backlog first reaches the target; until
+    /// then it is silent
+    started: bool,
+    output: Vec<f32>,
+}
+
+impl SourceMixer {
+    pub fn new(
+        layout: SourceLayout,
+        consumer: Consumer<f32>,
+        source_rate: u32,
+        sample_rate: u32,
+    ) -> Result<Self> {
+        let max_pending = (sample_rate * MAX_PENDING_MS / 1000) as usize;
+
+        Ok(Self {
+            layout,
+            consumer,
+            resampler: DriftResampler::new(source_rate, sample_rate)?,
+            pending: VecDeque::with_capacity(max_pending * 2),
+            target_pending: (sample_rate * TARGET_PENDING_MS / 1000) as usize,
+            max_pending,
+            backlog: 0.0,
+            started: false,
+            output: Vec::new(),
+        })
+    }
+
+    /// Combine a block of mono main-input samples with the second source
+    pub fn mix(&mut self, primary: &[f32]) -> &[f32] {
+        self.pull();
+        if !self.started && self.pending.len() >= self.target_pending {
+            self.started = true;
+            self.backlog = self.pending.len() as f64;
+        }
+        self.output.clear();
+
+        for &main in primary {
+            let second = if self.started {
+                self.pending.pop_front().unwrap_or(0.0)
+            } else {
+                0.0
+            };
+            match self.layout {
+                SourceLayout::Mixed => self.output.push((main + second).clamp(-1.0, 1.0)),
+                SourceLayout::Split => {
+                    self.output.push(main);
+                    self.output.push(second);
+                }
+            }
+        }
+
+        if self.started {
+            self.correct_drift();
+        }
+        if self.pending.len() > self.max_pending {
+            let excess = self.pending.len() - self.max_pending;
+            warn!(
+                "Second source fell {} samples out of step, dropping them",
+                excess
+            );
+            self.pending.drain(..excess);
+        }
+
+        &self.output
+    }
+
+    /// Steer the second source's rate so its backlog settles at the target: a
+    /// growing backlog means its clock runs fast, so it gets fewer samples
+    fn correct_drift(&mut self) {
+        self.backlog += (self.pending.len() as f64 - self.backlog) * BACKLOG_SMOOTHING;
+        let error = (self.backlog - self.target_pending as f64) / self.target_pending.max(1) as f64;
+        let correction =
+            (-error * MAX_DRIFT_CORRECTION).clamp(-MAX_DRIFT_CORRECTION, MAX_DRIFT_CORRECTION);
+        self.resampler.set_correction(correction);
+    }
+
+    /// Move everything the second source has captured into `pending`
+    fn pull(&mut self) {
+        let available = self.consumer.slots();
+        if available == 0 {
+            return;
+        }
+        if let Ok(chunk) = self.consumer.read_chunk(available) {
+            let (head, tail) = chunk.as_slices();
+            for data in [head, tail] {
+                if let Err(e) = self.resampler.process(data, &mut self.pending) {
+                    error!("Failed to resample second source: {}", e);
+                }
+            }
+            chunk.commit_all();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rtrb::RingBuffer;
+
+    /// Mix `seconds` of silent main input with a constant second source whose
+    /// clock runs `drift` fast, checking every block once the mixer settled
+    fn run_with_drift(drift: f64, seconds: usize) {
+        let rate = 16000;
+        let (mut producer, consumer) = RingBuffer::new(rate as usize);
+        let mut mixer = SourceMixer::new(SourceLayout::Mixed, consumer, rate, rate).unwrap();
+
+        let block = rate as usize / 100;
+        let mut owed = 0.0;
+        for i in 0..seconds * 100 {
+            owed += block as f64 * (1.0 + drift);
+            let frames = owed as usize;
+            owed -= frames as f64;
+            for _ in 0..frames {
+                producer.push(0.25).unwrap();
+            }
+
+            let output = mixer.mix(&vec![0.0; block]).to_vec();
+            assert!(mixer.pending.len() < mixer.max_pending, "block {}", i);
+            // Past the start-up, every sample comes from the second source
+            if i > 100 {
+                assert!(
+                    output.iter().all(|s| (s - 0.25).abs() < 0.01),
+                    "gap in block {}",
+                    i
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn follows_a_fast_second_source() {
+        run_with_drift(0.002, 60);
+    }
+
+    #[test]
+    fn follows_a_slow_second_source() {
+        run_with_drift(-0.002, 60);
+    }
+
+    #[test]
+    fn follows_matching_clocks() {
+        run_with_drift(0.0, 20);
+    }
+
+    #[test]
+    fn split_layout_keeps_sources_apart() {
+        let (mut producer, consumer) = RingBuffer::new(16000);
+        let mut mixer = SourceMixer::new(SourceLayout::Split, consumer, 16000, 16000).unwrap();
+        for _ in 0..2000 {
+            producer.push(0.5).unwrap();
+        }
+        let output = mixer.mix(&[0.1; 100]).to_vec();
+        assert_eq!(output.len(), 200);
+        assert!(output.chunks(2).all(|frame| frame[0] == 0.1));
+    }
+}
@@ -0,0 +1,276 @@
+use crate::recorder::disk_space::DiskSpace;
+use crate::recorder::encoder::RecordingFormat;
+use crate::recorder::error::RecorderError;
+use crate::recorder::recorder::Result;
+use log::{info, warn};
+use serde::Serialize;
+use std::sync::Mutex;
+use tauri::{AppHandle, Emitter};
+
+/// Lifecycle of a recording session
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum RecorderPhase {
+    /// No session is open
+    Idle,
+    /// Stream and file are open; audio is metered but not written
+    SessionReady,
+    Recording,
+    Paused,
+    /// The file is being finalized after a stop
+    Finalizing,
+    /// Capture or writing failed; the session can still be stopped or closed
+    Error,
+}
+
+impl RecorderPhase {
+    /// Whether a session in this phase may move to `to`
+    fn can_transition(self, to: RecorderPhase) -> bool {
+        use RecorderPhase::*;
+        matches!(
+            (self, to),
+            (Idle, SessionReady)
+                | (SessionReady, Recording)
+                | (Recording, Paused)
+                | (Paused, Recording)
+                // Stopping a ready session returns its last recording, e.g. after an auto-stop
+                | (SessionReady | Recording | Paused | Error, Finalizing)
+                | (Finalizing, SessionReady)
+                // A limit tripping while a stop drains the ring finalizes the same
+                // recording; whichever of the two finishes second finds the move made
+                | (Finalizing, Finalizing)
+                | (SessionReady, SessionReady)
+                | (_, Error)
+                | (_, Idle)
+        )
+    }
+}
+
+/// Payload for the `recorder-state-changed` event
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PhaseChanged {
+    pub recording_id: String,
+    pub from: RecorderPhase,
+    pub to: RecorderPhase,
+    /// Set when entering `Error`
+    pub error: Option<String>,
+}
+
+/// Snapshot of a session - returned to frontend by `get_recorder_status`
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RecorderStatus {
+    pub recording_id: Option<String>,
+    pub phase: RecorderPhase,
+    /// Input device currently captured, which changes after a failover
+    pub device_id: Option<String>,
+    /// What the device delivers
+    pub capture_sample_rate: u32,
+    pub capture_channels: u16,
+    /// What gets written to the file
+    pub sample_rate: u32,
+    pub channels: u16,
+    pub format: Option<RecordingFormat>,
+    pub file_path: Option<String>,
+    /// Audio written so far, pauses excluded
+    pub elapsed_seconds: f32,
+    pub paused_seconds: f32,
+    /// Size of the file on disk; audio still buffered in the writer isn't counted yet
+    pub bytes_written: u64,
+    /// Free space where the file is written; `None` if the platform can't tell
+    pub disk_space: Option<DiskSpace>,
+    /// Why the session is in `Error`
+    pub error: Option<String>,
+}
+
+impl RecorderStatus {
+    /// Status of a recorder with no open session
+    pub fn idle() -> Self {
+        Self {
+            recording_id: None,
+            phase: RecorderPhase::Idle,
+            device_id: None,
+            capture_sample_rate: 0,
+            capture_channels: 0,
+            sample_rate: 0,
+            channels: 0,
+            format: None,
+            file_path: None,
+            elapsed_seconds: 0.0,
+            paused_seconds: 0.0,
+            bytes_written: 0,
+            disk_space: None,
+            error: None,
+        }
+    }
+}
+
+/// Phase of one session, shared by the commands, the writer thread and the stream
+/// thread so each can report what it observes. Every change emits an event.
+pub struct SessionStatus {
+    recording_id: String,
+    app_handle: AppHandle,
+    inner: Mutex<StatusInner>,
+}
+
+struct StatusInner {
+    phase: RecorderPhase,
+    device_id: String,
+    error: Option<String>,
+}
+
+impl SessionStatus {
+    pub fn new(recording_id: String, device_id: String, app_handle: AppHandle) -> Self {
+        Self {
+            recording_id,
+            app_handle,
+            inner: Mutex::new(StatusInner {
+                phase: RecorderPhase::Idle,
+                device_id,
+                error: None,
+            }),
+        }
+    }
+
+    pub fn recording_id(&self) -> &str {
+        &self.recording_id
+    }
+
+    pub fn phase(&self) -> RecorderPhase {
+        self.lock().phase
+    }
+
+    pub fn device_id(&self) -> String {
+        self.lock().device_id.clone()
+    }
+
+    pub fn error(&self) -> Option<String> {
+        self.lock().error.clone()
+    }
+
+    /// The stream moved to another input device
+    pub fn set_device_id(&self, device_id: String) {
+        self.lock().device_id = device_id;
+    }
+
+    /// Check that the session may move to `to` without moving it, so commands
+    /// can refuse before touching the writer thread
+    pub fn check(&self, to: RecorderPhase) -> Result<()> {
+        let from = self.phase();
+        if from.can_transition(to) {
+            Ok(())
+        } else {
+            Err(invalid_transition(from, to))
+        }
+    }
+
+    /// Move to `to` if allowed from the current phase
+    pub fn transition(&self, to: RecorderPhase) -> Result<()> {
+        let from = {
+            let mut inner = self.lock();
+            let from = inner.phase;
+            if !from.can_transition(to) {
+                return Err(invalid_transition(from, to));
+            }
+            inner.phase = to;
+            if to != RecorderPhase::Error {
+                inner.error = None;
+            }
+            from
+        };
+        if from != to {
+            self.emit(from, to, None);
+        }
+        Ok(())
+    }
+
+    /// Enter `Error` from any phase
+    pub fn fail(&self, message: String) {
+        warn!(
+            "Recording session {} failed: {}",
+            self.recording_id, message
+        );
+        let from = {
+            let mut inner = self.lock();
+            let from = inner.phase;
+            inner.phase = RecorderPhase::Error;
+            inner.error = Some(message.clone());
+            from
+        };
+        self.emit(from, RecorderPhase::Error, Some(message));
+    }
+
+    fn emit(&self, from: RecorderPhase, to: RecorderPhase, error: Option<String>) {
+        info!(
+            "Recording session {}: {:?} -> {:?}",
+            self.recording_id, from, to
+        );
+        let _ = self.app_handle.emit(
+            "recorder-state-changed",
+            PhaseChanged {
+                recording_id: self.recording_id.clone(),
+                from,
+                to,
+                error,
+            },
+        );
+    }
+
+    /// The phase is plain data, so a panic elsewhere can't leave it inconsistent
+    fn lock(&self) -> std::sync::MutexGuard<'_, StatusInner> {
+        self.inner
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+fn invalid_transition(from: RecorderPhase, to: RecorderPhase) -> RecorderError {
+    RecorderError::InvalidState {
+        message: format!("Cannot go from {:?} to {:?}", from, to),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::RecorderPhase::{self, *};
+    use crate::recorder::auto_stop::{AutoStop, AutoStopReason};
+
+    fn step(from: RecorderPhase, to: RecorderPhase) -> RecorderPhase {
+        assert!(from.can_transition(to), "{:?} -> {:?}", from, to);
+        to
+    }
+
+    #[test]
+    fn limit_tripping_during_the_final_drain_lets_the_stop_finish() {
+        let mut auto_stop = AutoStop::new(Some(1.0), None, 16000, 1).unwrap();
+        assert!(auto_stop.process(&[0.1; 12000]).is_none());
+
+        // `stop_recording` moves to Finalizing, then the writer drains the ring
+        let mut phase = step(Recording, Finalizing);
+        assert_eq!(
+            auto_stop.process(&[0.1; 8000]),
+            Some((AutoStopReason::MaxDuration, 4000))
+        );
+        // `InputSink::auto_stop` finalizes first...
+        phase = step(phase, Finalizing);
+        phase = step(phase, SessionReady);
+        // ...and `stop_recording` then returns the same recording
+        assert_eq!(step(phase, SessionReady), SessionReady);
+    }
+
+    #[test]
+    fn stop_during_an_auto_stop_finishes_too() {
+        let phase = step(Recording, Finalizing);
+        let phase = step(phase, Finalizing);
+        let phase = step(phase, SessionReady);
+        assert_eq!(step(phase, SessionReady), SessionReady);
+    }
+
+    #[test]
+    fn finished_session_can_record_again() {
+        let phase = step(Finalizing, SessionReady);
+        assert_eq!(step(phase, Recording), Recording);
+        assert!(!Idle.can_transition(Finalizing));
+    }
+}
@@ -0,0 +1,415 @@
+use crate::recorder::output_converter::OutputConverter;
+use crate::recorder::recorder::Result;
+use crate::transcription::{transcribe_samples, LocalEngine, ModelManager};
+use log::{debug, error, info};
+use serde::{Deserialize, Serialize};
+use std::sync::mpsc;
+use std::thread;
+use tauri::{AppHandle, Emitter, Manager};
+
+/// Sample rate every local transcription engine expects
+const TRANSCRIBE_SAMPLE_RATE: u32 = 16000;
+
+/// Length of each window pauses are measured over (30ms at 16kHz)
+const WINDOW_SIZE: usize = 480;
+
+/// Transcribe while recording - sent from frontend
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StreamingConfig {
+    /// Local engine to transcribe with, loaded through the shared `ModelManager`
+    pub engine: LocalEngine,
+    pub model_path: String,
+    /// Spoken language hint (Whisper only)
+    pub language: Option<String>,
+    /// Longest chunk handed to the engine; chunks are cut here if no pause comes first
+    #[serde(default = "default_chunk_seconds")]
+    pub chunk_seconds: f32,
+    /// Cut chunks at pauses in speech instead of only at `chunk_seconds`
+    #[serde(default = "default_split_on_pauses")]
+    pub split_on_pauses: bool,
+    /// RMS level in dBFS below which audio counts as a pause
+    #[serde(default = "default_pause_threshold_db")]
+    pub pause_threshold_db: f32,
+    /// A pause must last this long before a chunk is cut at it
+    #[serde(default = "default_min_pause_ms")]
+    pub min_pause_ms: u32,
+    /// Chunks are never cut at a pause before they are this long
+    #[serde(default = "default_min_chunk_seconds")]
+    pub min_chunk_seconds: f32,
+}
+
+fn default_chunk_seconds() -> f32 {
+    10.0
+}
+
+fn default_split_on_pauses() -> bool {
+    true
+}
+
+fn default_pause_threshold_db() -> f32 {
+    -40.0
+}
+
+fn default_min_pause_ms() -> u32 {
+    500
+}
+
+fn default_min_chunk_seconds() -> f32 {
+    2.0
+}
+
+/// Payload for the `transcription-partial` event - the text of one chunk
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TranscriptionPartial {
+    pub recording_id: String,
+    pub chunk_index: u32,
+    pub start_seconds: f32,
+    pub duration_seconds: f32,
+    pub text: String,
+}
+
+/// Payload for the `transcription-final` event - every chunk stitched together
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TranscriptionFinal {
+    pub recording_id: String,
+    pub text: String,
+}
+
+/// Payload for the `transcription-error` event - streaming transcription could not run
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TranscriptionError {
+    pub recording_id: String,
+    pub message: String,
+}
+
+/// Messages from the writer thread (and `stop_recording`) to the transcription thread
+enum ChunkMsg {
+    Chunk {
+        index: u32,
+        start_seconds: f32,
+        samples: Vec<f32>,
+    },
+    /// A new recording started; forget the previous transcript
+    Reset,
+    /// The recording ended and its last chunk was sent. Replies with the stitched text.
+    Finish(Option<mpsc::Sender<String>>),
+}
+
+/// Handle for finishing the transcript from outside the writer thread
+#[derive(Clone)]
+pub struct StreamingHandle {
+    tx: mpsc::Sender<ChunkMsg>,
+}
+
+impl StreamingHandle {
+    /// Wait for the chunks already sent to be transcribed and return the full text
+    pub fn finish(&self) -> Option<String> {
+        let (reply_tx, reply_rx) = mpsc::channel();
+        self.tx.send(ChunkMsg::Finish(Some(reply_tx))).ok()?;
+        reply_rx.recv().ok()
+    }
+}
+
+/// Cuts recorded audio into chunks on the writer thread and sends them to the
+/// transcription thread, so nothing slow ever runs next to the ring buffer
+pub struct Chunker {
+    /// Brings the recording to 16kHz mono when it isn't already
+    converter: Option<OutputConverter>,
+    pending: PendingChunk,
+}
+
+impl Chunker {
+    /// Create the chunker and start the thread that transcribes its chunks. The
+    /// thread is not joined: it finishes the chunks it was given and exits once
+    /// the chunker and every handle are gone, so closing a session never waits on it.
+    pub fn new(
+        config: StreamingConfig,
+        sample_rate: u32,
+        channels: u16,
+        recording_id: String,
+        app_handle: AppHandle,
+    ) -> Result<(Self, StreamingHandle)> {
+        let converter = if sample_rate != TRANSCRIBE_SAMPLE_RATE || channels != 1 {
+            Some(OutputConverter::new(
+                sample_rate,
+                channels,
+                TRANSCRIBE_SAMPLE_RATE,
+                1,
+            )?)
+        } else {
+            None
+        };
+
+        let rate = TRANSCRIBE_SAMPLE_RATE as f32;
+        let max_len = ((config.chunk_seconds.max(1.0) * rate) as usize).max(WINDOW_SIZE);
+        let min_len = ((config.min_chunk_seconds.max(0.0) * rate) as usize).min(max_len);
+        let min_pause = config
+            .split_on_pauses
+            .then(|| (config.min_pause_ms * TRANSCRIBE_SAMPLE_RATE / 1000) as usize);
+        let pause_threshold = 10f32.powf(config.pause_threshold_db / 20.0);
+
+        let (tx, rx) = mpsc::channel();
+        thread::spawn(move || run_transcriber(rx, config, recording_id, app_handle));
+
+        let pending = PendingChunk {
+            tx: tx.clone(),
+            samples: Vec::with_capacity(max_len),
+            max_len,
+            min_len,
+            min_pause,
+            pause_threshold,
+            window_sum_sq: 0.0,
+            window_count: 0,
+            pause_len: 0,
+            has_speech: false,
+            next_index: 0,
+            chunk_start: 0,
+        };
+        Ok((Self { converter, pending }, StreamingHandle { tx }))
+    }
+
+    /// Start chunking a new recording
+    pub fn reset(&mut self) {
+        self.pending.clear();
+        self.pending.next_index = 0;
+        self.pending.chunk_start = 0;
+        let _ = self.pending.tx.send(ChunkMsg::Reset);
+    }
+
+    /// Add recorded audio, sending a chunk whenever one is complete
+    pub fn process(&mut self, data: &[f32]) {
+        match self.converter.as_mut() {
+            Some(converter) => match converter.process(data) {
+                Ok(converted) => self.pending.push(converted),
+                Err(e) => error!(
+                    "Failed to resample audio for streaming transcription: {}",
+                    e
+                ),
+            },
+            None => self.pending.push(data),
+        }
+    }
+
+    /// The recording stopped: send whatever is left as the last chunk
+    pub fn flush(&mut self) {
+        if let Some(converter) = self.converter.as_mut() {
+            match converter.flush() {
+                Ok(tail) => self.pending.push(tail),
+                Err(e) => error!("Failed to flush streaming transcription resampler: {}", e),
+            }
+        }
+        self.pending.send();
+    }
+
+    /// Have the full transcript stitched and emitted without waiting for it
+    pub fn finish(&self) {
+        let _ = self.pending.tx.send(ChunkMsg::Finish(None));
+    }
+}
+
+/// 16kHz mono audio collected for the next chunk
+struct PendingChunk {
+    tx: mpsc::Sender<ChunkMsg>,
+    samples: Vec<f32>,
+    max_len: usize,
+    min_len: usize,
+    /// Trailing pause (in samples) after which a chunk is cut, if splitting on pauses
+    min_pause: Option<usize>,
+    /// Linear RMS below which a window counts as a pause
+    pause_threshold: f32,
+    window_sum_sq: f32,
+    window_count: usize,
+    pause_len: usize,
+    has_speech: bool,
+    next_index: u32,
+    /// Samples of the recording before the current chunk
+    chunk_start: u64,
+}
+
+impl PendingChunk {
+    fn push(&mut self, data: &[f32]) {
+        for &sample in data {
+            self.samples.push(sample);
+            self.window_sum_sq += sample * sample;
+            self.window_count += 1;
+
+            if self.window_count == WINDOW_SIZE {
+                self.end_window();
+                let at_pause = self.min_pause.is_some_and(|min_pause| {
+                    self.pause_len >= min_pause && self.samples.len() >= self.min_len
+                });
+                if at_pause {
+                    self.send();
+                }
+            }
+
+            if self.samples.len() >= self.max_len {
+                self.send();
+            }
+        }
+    }
+
+    fn end_window(&mut self) {
+        let rms = (self.window_sum_sq / self.window_count as f32).sqrt();
+        if rms < self.pause_threshold {
+            self.pause_len += self.window_count;
+        } else {
+            self.pause_len = 0;
+            self.has_speech = true;
+        }
+        self.window_sum_sq = 0.0;
+        self.window_count = 0;
+    }
+
+    /// Hand the collected audio to the transcription thread. Chunks without any
+    /// speech are skipped, since engines tend to hallucinate text for silence.
+    fn send(&mut self) {
+        let len = self.samples.len();
+        if len == 0 {
+            return;
+        }
+        if self.window_count > 0 {
+            self.end_window();
+        }
+
+        if self.has_speech {
+            let start_seconds = self.chunk_start as f32 / TRANSCRIBE_SAMPLE_RATE as f32;
+            let samples = std::mem::replace(&mut self.samples, Vec::with_capacity(self.max_len));
+            let _ = self.tx.send(ChunkMsg::Chunk {
+                index: self.next_index,
+                start_seconds,
+                samples,
+            });
+            self.next_index += 1;
+        }
+
+        self.chunk_start += len as u64;
+        self.clear();
+    }
+
+    fn clear(&mut self) {
+        self.samples.clear();
+        self.window_sum_sq = 0.0;
+        self.window_count = 0;
+        self.pause_len = 0;
+        self.has_speech = false;
+    }
+}
+
+/// Transcription thread loop: transcribes chunks in order and emits each result
+fn run_transcriber(
+    rx: mpsc::Receiver<ChunkMsg>,
+    config: StreamingConfig,
+    recording_id: String,
+    app_handle: AppHandle,
+) {
+    // Not guaranteed on a detached thread, e.g. while the app is shutting down
+    let Some(model_manager) = app_handle.try_state::<ModelManager>() else {
+        let message = "Model manager is not available, streaming transcription is off".to_string();
+        error!("{}", message);
+        let _ = app_handle.emit(
+            "transcription-error",
+            TranscriptionError {
+                recording_id,
+                message,
+            },
+        );
+        // Dropping the receiver makes `StreamingHandle::finish` return no transcript
+        return;
+    };
+    let mut texts: Vec<String> = Vec::new();
+    // Stitched text of the last finished recording, returned again on repeat requests
+    let mut finished: Option<String> = None;
+
+    while let Ok(msg) = rx.recv() {
+        match msg {
+            ChunkMsg::Chunk {
+                index,
+                start_seconds,
+                samples,
+            } => {
+                let duration_seconds = samples.len() as f32 / TRANSCRIBE_SAMPLE_RATE as f32;
+                // The previous chunk gives Whisper context across the cut
+                let prompt = texts.iter().rev().find(|t| !t.is_empty()).cloned();
+
+                let text = match transcribe_samples(
+                    &model_manager,
+                    config.engine,
+                    &config.model_path,
+                    config.language.clone(),
+                    prompt,
+                    samples,
+                ) {
+                    Ok(text) => text,
+                    Err(e) => {
+                        error!("Failed to transcribe chunk {}: {:?}", index, e);
+                        String::new()
+                    }
+                };
+                debug!(
+                    "Transcribed chunk {} ({:.2}s at {:.2}s): {} characters",
+                    index,
+                    duration_seconds,
+                    start_seconds,
+                    text.len()
+                );
+
+                let _ = app_handle.emit(
+                    "transcription-partial",
+                    TranscriptionPartial {
+                        recording_id: recording_id.clone(),
+                        chunk_index: index,
+                        start_seconds,
+                        duration_seconds,
+                        text: text.clone(),
+                    },
+                );
+                texts.push(text);
+                finished = None;
+            }
+            ChunkMsg::Reset => {
+                texts.clear();
+                finished = None;
+            }
+            ChunkMsg::Finish(reply_tx) => {
+                let text = match &finished {
+                    Some(text) => text.clone(),
+                    None => {
+                        let text = stitch(&texts);
+                        info!(
+                            "Streaming transcription finished: {} chunks, {} characters",
+                            texts.len(),
+                            text.len()
+                        );
+                        let _ = app_handle.emit(
+                            "transcription-final",
+                            TranscriptionFinal {
+                                recording_id: recording_id.clone(),
+                                text: text.clone(),
+                            },
+                        );
+                        finished = Some(text.clone());
+                        text
+                    }
+                };
+                if let Some(reply_tx) = reply_tx {
+                    let _ = reply_tx.send(text);
+                }
+            }
+        }
+    }
+}
+
+/// Join chunk transcripts with single spaces, skipping chunks that produced nothing
+fn stitch(texts: &[String]) -> String {
+    texts
+        .iter()
+        .map(|text| text.trim())
+        .filter(|text| !text.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
@@ -0,0 +1,666 @@
+use crate::recorder::error::RecorderError;
+use crate::recorder::output_converter::OutputConverter;
+use crate::recorder::recorder::Result;
+use crate::recorder::wav_writer::{WavSampleFormat, WavWriter};
+use log::{debug, error, info, warn};
+use ort::session::Session;
+use ort::value::Tensor;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::path::PathBuf;
+use tauri::{AppHandle, Emitter};
+
+/// Sample rate used for detection and for the segment files we write.
+/// Silero only accepts 8kHz or 16kHz, and 16kHz mono is what every local
+/// transcription engine wants anyway.
+const VAD_SAMPLE_RATE: u32 = 16000;
+
+/// Samples per detection frame (32ms at 16kHz, the Silero v5 window size)
+const FRAME_SIZE: usize = 512;
+
+/// Silero v5 expects the last 64 samples of the previous frame prepended to each input
+const SILERO_CONTEXT_SIZE: usize = 64;
+
+/// Voice activity detection settings - sent from frontend
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VadConfig {
+    /// Path to a Silero VAD ONNX model. Falls back to energy detection when absent.
+    pub model_path: Option<String>,
+    /// Speech probability threshold for the Silero model (0.0 - 1.0)
+    #[serde(default = "default_speech_threshold")]
+    pub speech_threshold: f32,
+    /// RMS level in dBFS above which a frame counts as speech for energy detection
+    #[serde(default = "default_energy_threshold_db")]
+    pub energy_threshold_db: f32,
+    /// Speech must last this long before a segment is started
+    #[serde(default = "default_min_speech_ms")]
+    pub min_speech_ms: u32,
+    /// Silence must last this long before a segment is finished
+    #[serde(default = "default_min_silence_ms")]
+    pub min_silence_ms: u32,
+    /// Audio kept before the detected speech start so the first syllable isn't clipped
+    #[serde(default = "default_speech_pad_ms")]
+    pub speech_pad_ms: u32,
+}
+
+fn default_speech_threshold() -> f32 {
+    0.5
+}
+
+fn default_energy_threshold_db() -> f32 {
+    -40.0
+}
+
+fn default_min_speech_ms() -> u32 {
+    250
+}
+
+fn default_min_silence_ms() -> u32 {
+    800
+}
+
+fn default_speech_pad_ms() -> u32 {
+    300
+}
+
+/// Payload for the `vad-speech-start` event
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VadSpeechStart {
+    pub recording_id: String,
+    pub segment_index: u32,
+    pub start_seconds: f32,
+}
+
+/// Payload for the `vad-speech-end` event - a finished speech segment on disk
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VadSegment {
+    pub recording_id: String,
+    pub segment_index: u32,
+    pub file_path: String,
+    pub sample_rate: u32,
+    pub start_seconds: f32,
+    pub duration_seconds: f32,
+}
+
+/// Payload for the `vad-segment-failed` event - speech that could not be saved
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VadSegmentFailed {
+    pub recording_id: String,
+    pub segment_index: u32,
+    pub error: String,
+}
+
+/// Produces a speech probability for each 512-sample frame
+enum Detector {
+    Energy { threshold_db: f32 },
+    Silero(SileroModel),
+}
+
+impl Detector {
+    fn speech_probability(&mut self, frame: &[f32]) -> f32 {
+        match self {
+            Detector::Energy { threshold_db } => {
+                let mean_square =
+                    frame.iter().map(|s| s * s).sum::<f32>() / frame.len() as f32;
+                let rms_db = 10.0 * mean_square.max(1e-10).log10();
+                if rms_db >= *threshold_db {
+                    1.0
+                } else {
+                    0.0
+                }
+            }
+            Detector::Silero(model) => match model.predict(frame) {
+                Ok(probability) => probability,
+                Err(e) => {
+                    // Treat inference failures as silence rather than tearing down the stream
+                    warn!("Silero VAD inference failed: {}", e);
+                    0.0
+                }
+            },
+        }
+    }
+}
+
+/// Stateful wrapper around the Silero VAD v5 ONNX model
+struct SileroModel {
+    session: Session,
+    state: Vec<f32>,
+    context: Vec<f32>,
+}
+
+impl SileroModel {
+    fn load(model_path: &str) -> Result<Self> {
+        let session = Session::builder()
+            .and_then(|builder| builder.with_intra_threads(1))
+            .and_then(|builder| builder.commit_from_file(model_path))
+            .map_err(|e| RecorderError::Processing {
+                message: format!("Failed to load Silero VAD model: {}", e),
+            })?;
+
+        info!("Loaded Silero VAD model from {}", model_path);
+
+        Ok(Self {
+            session,
+            state: vec![0.0; 2 * 128],
+            context: vec![0.0; SILERO_CONTEXT_SIZE],
+        })
+    }
+
+    fn predict(&mut self, frame: &[f32]) -> ort::Result<f32> {
+        let mut input = Vec::with_capacity(SILERO_CONTEXT_SIZE + frame.len());
+        input.extend_from_slice(&self.context);
+        input.extend_from_slice(frame);
+        self.context
+            .copy_from_slice(&frame[frame.len() - SILERO_CONTEXT_SIZE..]);
+
+        let input = Tensor::from_array(([1usize, input.len()], input))?;
+        let state = Tensor::from_array(([2usize, 1, 128], self.state.clone()))?;
+        let sr = Tensor::from_array(((), vec![VAD_SAMPLE_RATE as i64]))?;
+
+        let outputs = self
+            .session
+            .run(ort::inputs!["input" => input, "state" => state, "sr" => sr])?;
+
+        let (_, probability) = outputs["output"].try_extract_tensor::<f32>()?;
+        let probability = probability.first().copied().unwrap_or(0.0);
+        let (_, new_state) = outputs["stateN"].try_extract_tensor::<f32>()?;
+        self.state.copy_from_slice(new_state);
+
+        Ok(probability)
+    }
+}
+
+/// Linear resampler from the device rate down to 16kHz mono. It has no
+/// anti-alias filter, which detection doesn't mind, so it only feeds the
+/// detector; the audio written to segment files goes through `SegmentAudio`.
+struct LinearResampler {
+    step: f64,
+    position: f64,
+    previous: f32,
+}
+
+impl LinearResampler {
+    fn new(input_rate: u32) -> Self {
+        Self {
+            step: input_rate as f64 / VAD_SAMPLE_RATE as f64,
+            position: 0.0,
+            previous: 0.0,
+        }
+    }
+
+    fn process(&mut self, input: &[f32], output: &mut Vec<f32>) {
+        for &sample in input {
+            while self.position <= 1.0 {
+                let t = self.position as f32;
+                output.push(self.previous + (sample - self.previous) * t);
+                self.position += self.step;
+            }
+            self.position -= 1.0;
+            self.previous = sample;
+        }
+    }
+}
+
+/// Recent audio at 16kHz mono for the segment files, converted with the same
+/// band-limited resampler as the recording so nothing above 8kHz folds back
+/// into the speech band. Addressed by position in samples since the session
+/// started, which lines up with the detector's positions.
+struct SegmentAudio {
+    converter: OutputConverter,
+    samples: VecDeque<f32>,
+    /// Position of the first sample in `samples`
+    start: u64,
+}
+
+impl SegmentAudio {
+    fn new(input_sample_rate: u32, channels: u16) -> Result<Self> {
+        Ok(Self {
+            converter: OutputConverter::new(input_sample_rate, channels, VAD_SAMPLE_RATE, 1)?,
+            samples: VecDeque::new(),
+            start: 0,
+        })
+    }
+
+    /// Convert interleaved input. The output may lag the input by up to one
+    /// resampler chunk.
+    fn push(&mut self, data: &[f32]) -> Result<()> {
+        let converted = self.converter.process(data)?;
+        self.samples.extend(converted);
+        Ok(())
+    }
+
+    /// Convert whatever the resampler still buffers; the stream ends here
+    fn flush(&mut self) -> Result<()> {
+        let converted = self.converter.flush()?;
+        self.samples.extend(converted);
+        Ok(())
+    }
+
+    /// Position just past the last converted sample
+    fn end(&self) -> u64 {
+        self.start + self.samples.len() as u64
+    }
+
+    /// Samples from `from` up to `to`, limited to what is still held
+    fn range(&self, from: u64, to: u64) -> Vec<f32> {
+        let from = from.clamp(self.start, self.end());
+        let to = to.clamp(from, self.end());
+        self.samples
+            .range((from - self.start) as usize..(to - self.start) as usize)
+            .copied()
+            .collect()
+    }
+
+    /// Forget the audio before `position`
+    fn discard_before(&mut self, position: u64) {
+        let count = position
+            .saturating_sub(self.start)
+            .min(self.samples.len() as u64);
+        self.samples.drain(..count as usize);
+        self.start += count;
+    }
+}
+
+enum SegmentState {
+    Silent,
+    /// Speech detected but not long enough yet to open a segment
+    Pending { speech_frames: u32 },
+    Speaking { silence_frames: u32 },
+}
+
+/// Voice activity detection stage fed from the input stream.
+/// Splits the live audio into speech segments, writes each one to its own WAV
+/// file and announces them to the frontend through Tauri events.
+///
+/// Positions are counted in 16kHz samples since the session started.
+pub struct VadStage {
+    detector: Detector,
+    config: VadConfig,
+    channels: usize,
+    resampler: LinearResampler,
+    resampled: Vec<f32>,
+    frame: Vec<f32>,
+    /// What the segment files are written from
+    audio: SegmentAudio,
+    /// Audio before the speech each segment starts with
+    pad_capacity: usize,
+    /// Where the speech being confirmed in the pending state began
+    pending_start: u64,
+    state: SegmentState,
+    segment_writer: Option<WavWriter>,
+    /// Position the open segment has been written up to
+    written_until: u64,
+    /// Where the open segment's speech ended, while its audio is still being converted
+    segment_end: Option<u64>,
+    /// End of the previous segment, so padding never repeats its audio
+    last_segment_end: u64,
+    segment_index: u32,
+    /// Every segment file created this session, so a cancel can remove them
+    segment_paths: Vec<PathBuf>,
+    segment_start_sample: u64,
+    samples_processed: u64,
+    output_folder: PathBuf,
+    recording_id: String,
+    app_handle: AppHandle,
+}
+
+impl VadStage {
+    pub fn new(
+        config: VadConfig,
+        input_sample_rate: u32,
+        channels: u16,
+        output_folder: PathBuf,
+        recording_id: String,
+        app_handle: AppHandle,
+    ) -> Result<Self> {
+        let detector = match &config.model_path {
+            Some(path) => Detector::Silero(SileroModel::load(path)?),
+            None => Detector::Energy {
+                threshold_db: config.energy_threshold_db,
+            },
+        };
+
+        let pad_capacity = ms_to_samples(config.speech_pad_ms);
+
+        info!(
+            "VAD enabled: {} detection, min speech {}ms, min silence {}ms, pad {}ms",
+            if config.model_path.is_some() { "Silero" } else { "energy" },
+            config.min_speech_ms,
+            config.min_silence_ms,
+            config.speech_pad_ms
+        );
+
+        Ok(Self {
+            detector,
+            config,
+            channels: channels.max(1) as usize,
+            resampler: LinearResampler::new(input_sample_rate),
+            resampled: Vec::new(),
+            frame: Vec::with_capacity(FRAME_SIZE),
+            audio: SegmentAudio::new(input_sample_rate, channels)?,
+            pad_capacity,
+            pending_start: 0,
+            state: SegmentState::Silent,
+            segment_writer: None,
+            written_until: 0,
+            segment_end: None,
+            last_segment_end: 0,
+            segment_index: 0,
+            segment_paths: Vec::new(),
+            segment_start_sample: 0,
+            samples_processed: 0,
+            output_folder,
+            recording_id,
+            app_handle,
+        })
+    }
+
+    /// Feed interleaved f32 samples straight from the input stream
+    pub fn process_samples(&mut self, data: &[f32]) {
+        if let Err(e) = self.audio.push(data) {
+            error!("Failed to resample VAD segment audio: {}", e);
+        }
+
+        // Downmix to mono, then bring it to the detection rate
+        let mono: Vec<f32> = data
+            .chunks(self.channels)
+            .map(|frame| frame.iter().sum::<f32>() / frame.len() as f32)
+            .collect();
+
+        let mut resampled = std::mem::take(&mut self.resampled);
+        resampled.clear();
+        self.resampler.process(&mono, &mut resampled);
+
+        for &sample in &resampled {
+            self.frame.push(sample);
+            if self.frame.len() == FRAME_SIZE {
+                let frame = std::mem::replace(&mut self.frame, Vec::with_capacity(FRAME_SIZE));
+                self.process_frame(&frame);
+            }
+        }
+
+        self.resampled = resampled;
+
+        self.write_segment();
+        self.audio.discard_before(self.retain_from());
+    }
+
+    fn process_frame(&mut self, frame: &[f32]) {
+        let probability = self.detector.speech_probability(frame);
+        let min_speech_frames = ms_to_frames(self.config.min_speech_ms);
+        let min_silence_frames = ms_to_frames(self.config.min_silence_ms);
+
+        let (is_speech, is_silence) = match self.detector {
+            // Hysteresis keeps a segment open through short dips in probability
+            Detector::Silero(_) => (
+                probability >= self.config.speech_threshold,
+                probability < (self.config.speech_threshold - 0.15).max(0.01),
+            ),
+            Detector::Energy { .. } => (probability >= 0.5, probability < 0.5),
+        };
+
+        let frame_start = self.samples_processed;
+        self.samples_processed += frame.len() as u64;
+
+        self.state = match std::mem::replace(&mut self.state, SegmentState::Silent) {
+            SegmentState::Silent if is_speech => {
+                self.pending_start = frame_start;
+                if min_speech_frames <= 1 {
+                    self.start_speaking(frame_start)
+                } else {
+                    SegmentState::Pending { speech_frames: 1 }
+                }
+            }
+            SegmentState::Silent => SegmentState::Silent,
+            SegmentState::Pending { speech_frames } if is_speech => {
+                if speech_frames + 1 >= min_speech_frames {
+                    self.start_speaking(self.pending_start)
+                } else {
+                    SegmentState::Pending {
+                        speech_frames: speech_frames + 1,
+                    }
+                }
+            }
+            // Too short to be speech - it stays available as padding
+            SegmentState::Pending { .. } => SegmentState::Silent,
+            SegmentState::Speaking { silence_frames } => {
+                let silence_frames = if is_silence { silence_frames + 1 } else { 0 };
+                if silence_frames >= min_silence_frames {
+                    self.segment_end = Some(self.samples_processed);
+                    SegmentState::Silent
+                } else {
+                    SegmentState::Speaking { silence_frames }
+                }
+            }
+        };
+    }
+
+    /// Oldest position a segment may still need: the padding before the
+    /// current or pending speech, and whatever the open segment hasn't written
+    fn retain_from(&self) -> u64 {
+        let from = match self.state {
+            SegmentState::Pending { .. } => self.pending_start,
+            _ => self.samples_processed,
+        }
+        .saturating_sub(self.pad_capacity as u64);
+        if self.segment_writer.is_some() {
+            from.min(self.written_until)
+        } else {
+            from
+        }
+    }
+
+    /// Open a segment for the speech that just began, or stay silent if its
+    /// file can't be created
+    fn start_speaking(&mut self, speech_start_sample: u64) -> SegmentState {
+        // The previous segment's audio may still be in the resampler; it ends
+        // with what has been converted so far
+        if self.segment_writer.is_some() {
+            self.write_segment();
+            self.close_segment();
+        }
+
+        match self.open_segment(speech_start_sample) {
+            Ok(()) => SegmentState::Speaking { silence_frames: 0 },
+            Err(e) => {
+                error!("Failed to create VAD segment file: {}", e);
+                let _ = self.app_handle.emit(
+                    "vad-segment-failed",
+                    VadSegmentFailed {
+                        recording_id: self.recording_id.clone(),
+                        segment_index: self.segment_index,
+                        error: e.to_string(),
+                    },
+                );
+                SegmentState::Silent
+            }
+        }
+    }
+
+    fn open_segment(&mut self, speech_start_sample: u64) -> std::io::Result<()> {
+        let file_path = self.output_folder.join(format!(
+            "{}-vad-{:03}.wav",
+            self.recording_id, self.segment_index
+        ));
+
+        // 16-bit PCM at 16kHz mono, what speech-to-text services expect
+        let writer = WavWriter::with_format(
+            file_path.clone(),
+            VAD_SAMPLE_RATE,
+            1,
+            WavSampleFormat::Pcm16,
+        )?;
+        self.segment_paths.push(file_path);
+
+        let pad = speech_start_sample
+            .saturating_sub(self.last_segment_end)
+            .min(self.pad_capacity as u64);
+        self.segment_start_sample = speech_start_sample - pad;
+        self.written_until = self.segment_start_sample;
+        self.segment_end = None;
+        self.segment_writer = Some(writer);
+
+        debug!("VAD speech start: segment {}", self.segment_index);
+        let _ = self.app_handle.emit(
+            "vad-speech-start",
+            VadSpeechStart {
+                recording_id: self.recording_id.clone(),
+                segment_index: self.segment_index,
+                start_seconds: self.segment_start_sample as f32 / VAD_SAMPLE_RATE as f32,
+            },
+        );
+        Ok(())
+    }
+
+    /// Write the converted audio the open segment is missing, and close it
+    /// once it reaches the end of the speech
+    fn write_segment(&mut self) {
+        let Some(writer) = self.segment_writer.as_mut() else {
+            return;
+        };
+
+        let end = self
+            .segment_end
+            .map_or(self.audio.end(), |end| end.min(self.audio.end()));
+        if end > self.written_until {
+            let samples = self.audio.range(self.written_until, end);
+            if let Err(e) = writer.write_samples_f32(&samples) {
+                error!("Failed to write VAD segment: {}", e);
+            }
+            self.written_until = end;
+        }
+
+        if self
+            .segment_end
+            .is_some_and(|segment_end| self.written_until >= segment_end)
+        {
+            self.close_segment();
+        }
+    }
+
+    fn close_segment(&mut self) {
+        let Some(mut writer) = self.segment_writer.take() else {
+            return;
+        };
+        self.last_segment_end = self.segment_end.take().unwrap_or(self.written_until);
+
+        if let Err(e) = writer.finalize() {
+            error!("Failed to finalize VAD segment: {}", e);
+            return;
+        }
+
+        let segment = VadSegment {
+            recording_id: self.recording_id.clone(),
+            segment_index: self.segment_index,
+            file_path: writer.get_file_path().to_string_lossy().to_string(),
+            sample_rate: VAD_SAMPLE_RATE,
+            start_seconds: self.segment_start_sample as f32 / VAD_SAMPLE_RATE as f32,
+            duration_seconds: writer.get_duration_seconds(),
+        };
+
+        info!(
+            "VAD speech end: segment {} ({:.2}s) at {}",
+            segment.segment_index, segment.duration_seconds, segment.file_path
+        );
+        let _ = self.app_handle.emit("vad-speech-end", segment);
+
+        self.segment_index += 1;
+    }
+
+    /// Finish any segment still open (called when the session closes)
+    pub fn flush(&mut self) {
+        if matches!(self.state, SegmentState::Speaking { .. }) {
+            self.segment_end = Some(self.samples_processed);
+        }
+        if self.segment_writer.is_some() {
+            // Push the resampler's tail out so the segment ends with the speech
+            if let Err(e) = self.audio.flush() {
+                error!("Failed to resample VAD segment audio: {}", e);
+            }
+            self.write_segment();
+            self.close_segment();
+        }
+        self.state = SegmentState::Silent;
+    }
+
+    /// Drop any open segment and delete every segment file written this session
+    pub fn discard(&mut self) {
+        self.segment_writer = None;
+        self.segment_end = None;
+        self.state = SegmentState::Silent;
+        for path in self.segment_paths.drain(..) {
+            std::fs::remove_file(&path).ok(); // Ignore errors
+        }
+        debug!("Deleted VAD segments of {}", self.recording_id);
+    }
+}
+
+fn ms_to_samples(ms: u32) -> usize {
+    (VAD_SAMPLE_RATE as u64 * ms as u64 / 1000) as usize
+}
+
+fn ms_to_frames(ms: u32) -> u32 {
+    (ms_to_samples(ms) as u32).div_ceil(FRAME_SIZE as u32)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f32::consts::PI;
+
+    fn sine(frequency: f32, sample_rate: u32, len: usize) -> Vec<f32> {
+        (0..len)
+            .map(|i| (2.0 * PI * frequency * i as f32 / sample_rate as f32).sin() * 0.5)
+            .collect()
+    }
+
+    fn rms(samples: &[f32]) -> f32 {
+        (samples.iter().map(|s| s * s).sum::<f32>() / samples.len() as f32).sqrt()
+    }
+
+    #[test]
+    fn segment_audio_keeps_content_above_8khz_out_of_the_speech_band() {
+        let mut audio = SegmentAudio::new(48000, 1).unwrap();
+        // 12kHz would alias to 4kHz at 16kHz without filtering
+        for block in sine(12000.0, 48000, 48000).chunks(480) {
+            audio.push(block).unwrap();
+        }
+        let converted = audio.range(2000, audio.end());
+        assert!(rms(&converted) < 0.01, "rms {}", rms(&converted));
+    }
+
+    #[test]
+    fn segment_audio_lines_up_with_detector_positions() {
+        let mut audio = SegmentAudio::new(48000, 2).unwrap();
+        // A step from silence to a tone at exactly 1s, in stereo
+        let mut input = vec![0.0; 48000];
+        input.extend(sine(440.0, 48000, 48000));
+        let stereo: Vec<f32> = input.iter().flat_map(|&s| [s, s]).collect();
+        for block in stereo.chunks(960) {
+            audio.push(block).unwrap();
+        }
+        audio.flush().unwrap();
+        assert_eq!(audio.end(), 32000);
+
+        // Quiet well before the step, loud well after it
+        assert!(rms(&audio.range(15000, 15900)) < 0.01);
+        assert!(rms(&audio.range(16100, 17000)) > 0.3);
+    }
+
+    #[test]
+    fn segment_audio_forgets_old_samples() {
+        let mut audio = SegmentAudio::new(16000, 1).unwrap();
+        audio.push(&sine(440.0, 16000, 1000)).unwrap();
+        audio.discard_before(600);
+        assert_eq!(audio.range(0, 1000).len(), 400);
+        // Discarding past the end never moves positions beyond the audio
+        audio.discard_before(5000);
+        assert_eq!(audio.end(), 1000);
+        audio.push(&[0.25; 10]).unwrap();
+        assert_eq!(audio.range(1000, 1010), vec![0.25; 10]);
+    }
+}
@@ -1,33 +1,91 @@
 use std::fs::File;
 use std::io::{self, BufWriter, Seek, SeekFrom, Write};
 use std::path::PathBuf;
-use std::time::Instant;
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
 use log::{debug, info};
+use serde::{Deserialize, Serialize};
+
+/// Size of the ds64 chunk body: RIFF size, data size, sample count (u64 each) and table length (u32)
+const DS64_CHUNK_SIZE: u32 = 28;
+
+/// Size of one cue point in the `cue ` chunk
+const CUE_POINT_SIZE: u32 = 24;
+
+/// Sample encoding written to the WAV data chunk
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum WavSampleFormat {
+    /// 32-bit IEEE float
+    #[default]
+    Float32,
+    /// 16-bit integer PCM - what the local transcription models consume
+    Pcm16,
+    /// 24-bit integer PCM
+    Pcm24,
+}
+
+impl WavSampleFormat {
+    pub fn bits_per_sample(&self) -> u16 {
+        match self {
+            WavSampleFormat::Float32 => 32,
+            WavSampleFormat::Pcm16 => 16,
+            WavSampleFormat::Pcm24 => 24,
+        }
+    }
+}
 
-/// WAV file writer that supports progressive writing with header updates
+/// WAV file writer that supports progressive writing with header updates.
+///
+/// A `JUNK` chunk is reserved right after the RIFF header. Once the file grows
+/// past the 4 GiB a 32-bit RIFF size can describe, the header is rewritten as
+/// RF64 (EBU Tech 3306) and that chunk becomes the `ds64` chunk holding the
+/// 64-bit sizes.
+///
+/// Markers and `LIST INFO` tags are written after the data chunk on finalize,
+/// and dropped again if more samples are written afterwards.
 pub struct WavWriter {
     writer: BufWriter<File>,
     sample_rate: u32,
     channels: u16,
-    #[allow(dead_code)]
-    bits_per_sample: u16,
+    sample_format: WavSampleFormat,
     bytes_per_sample: u16,
     data_chunk_size_pos: u64,
     riff_chunk_size_pos: u64,
+    ds64_chunk_pos: u64,
+    is_rf64: bool,
     samples_written: u64,
     last_header_update: Instant,
     file_path: PathBuf,
+    /// Cue points as (frame, label); the cue ID is the position in this list plus one
+    markers: Vec<(u64, String)>,
+    /// `LIST INFO` tags as (chunk ID, text)
+    info: Vec<([u8; 4], String)>,
+    /// Bytes of chunks currently written after the sample data
+    trailer_len: u64,
 }
 
 impl WavWriter {
-    /// Create a new WAV file and write initial headers
+    /// Create a new 32-bit float WAV file and write initial headers
     pub fn new(file_path: PathBuf, sample_rate: u32, channels: u16) -> io::Result<Self> {
+        Self::with_format(file_path, sample_rate, channels, WavSampleFormat::Float32)
+    }
+
+    /// Create a new WAV file with the given sample encoding and write initial headers
+    pub fn with_format(
+        file_path: PathBuf,
+        sample_rate: u32,
+        channels: u16,
+        sample_format: WavSampleFormat,
+    ) -> io::Result<Self> {
         let file = File::create(&file_path)?;
         let mut writer = BufWriter::new(file);
 
-        // We'll use 32-bit float format for consistency with the current implementation
-        let bits_per_sample = 32;
+        let bits_per_sample = sample_format.bits_per_sample();
         let bytes_per_sample = bits_per_sample / 8;
+        let audio_format: u16 = match sample_format {
+            WavSampleFormat::Float32 => 3, // IEEE Float
+            WavSampleFormat::Pcm16 | WavSampleFormat::Pcm24 => 1, // PCM
+        };
 
         // Write initial WAV header with placeholder sizes
         // We'll update these as we write samples
@@ -38,10 +96,16 @@ impl WavWriter {
         writer.write_all(&[0xFF, 0xFF, 0xFF, 0xFF])?; // Placeholder for file size - 8
         writer.write_all(b"WAVE")?;
 
+        // Reserve room for a ds64 chunk in case the recording outgrows RIFF
+        let ds64_chunk_pos = writer.stream_position()?;
+        writer.write_all(b"JUNK")?;
+        writer.write_all(&DS64_CHUNK_SIZE.to_le_bytes())?;
+        writer.write_all(&[0; DS64_CHUNK_SIZE as usize])?;
+
         // fmt chunk
         writer.write_all(b"fmt ")?;
         writer.write_all(&16u32.to_le_bytes())?; // Subchunk1Size (16 for PCM)
-        writer.write_all(&3u16.to_le_bytes())?; // AudioFormat (3 for IEEE Float)
+        writer.write_all(&audio_format.to_le_bytes())?; // AudioFormat
         writer.write_all(&channels.to_le_bytes())?;
         writer.write_all(&sample_rate.to_le_bytes())?;
         let byte_rate = sample_rate * channels as u32 * bytes_per_sample as u32;
@@ -58,72 +122,142 @@ impl WavWriter {
         writer.flush()?;
 
         info!(
-            "Created WAV file at {:?}: {}Hz, {} channels, {}-bit float",
-            file_path, sample_rate, channels, bits_per_sample
+            "Created WAV file at {:?}: {}Hz, {} channels, {:?}",
+            file_path, sample_rate, channels, sample_format
         );
 
         Ok(Self {
             writer,
             sample_rate,
             channels,
-            bits_per_sample,
+            sample_format,
             bytes_per_sample,
             data_chunk_size_pos,
             riff_chunk_size_pos,
+            ds64_chunk_pos,
+            is_rf64: false,
             samples_written: 0,
             last_header_update: Instant::now(),
             file_path,
+            markers: Vec::new(),
+            info: vec![
+                (*b"ICRD", creation_date()),
+                (
+                    *b"ISFT",
+                    concat!("Whispering ", env!("CARGO_PKG_VERSION")).to_string(),
+                ),
+            ],
+            trailer_len: 0,
         })
     }
 
-    /// Write f32 samples to the WAV file
-    pub fn write_samples_f32(&mut self, samples: &[f32]) -> io::Result<()> {
-        // Write samples as little-endian f32
-        for sample in samples {
-            self.writer.write_all(&sample.to_le_bytes())?;
+    /// Set the `INAM` (title) tag written on finalize
+    pub fn set_title(&mut self, title: String) {
+        self.info.retain(|(id, _)| id != b"INAM");
+        self.info.insert(0, (*b"INAM", title));
+    }
+
+    /// Add a cue point at `frame` (sample frames from the start of the data),
+    /// written as a `cue ` point with a `labl` in `LIST adtl` on finalize
+    pub fn add_marker(&mut self, frame: u64, label: String) {
+        self.markers.push((frame, label));
+    }
+
+    /// Chunks written after the sample data: cue points, their labels and the
+    /// INFO tags, preceded by the data chunk's pad byte when its size is odd
+    fn trailer_chunks(&self) -> Vec<u8> {
+        let mut trailer = Vec::new();
+        if (self.samples_written * self.bytes_per_sample as u64) % 2 == 1 {
+            trailer.push(0);
         }
 
-        self.samples_written += samples.len() as u64;
+        if !self.markers.is_empty() {
+            let count = self.markers.len() as u32;
+            trailer.extend_from_slice(b"cue ");
+            trailer.extend_from_slice(&(4 + count * CUE_POINT_SIZE).to_le_bytes());
+            trailer.extend_from_slice(&count.to_le_bytes());
+            for (index, (frame, _)) in self.markers.iter().enumerate() {
+                // 32-bit positions cover about a day at 48kHz; later cues are pinned to the limit
+                let position = (*frame).min(u32::MAX as u64) as u32;
+                trailer.extend_from_slice(&(index as u32 + 1).to_le_bytes()); // Cue ID
+                trailer.extend_from_slice(&position.to_le_bytes()); // Play order position
+                trailer.extend_from_slice(b"data");
+                trailer.extend_from_slice(&0u32.to_le_bytes()); // Chunk start
+                trailer.extend_from_slice(&0u32.to_le_bytes()); // Block start
+                trailer.extend_from_slice(&position.to_le_bytes()); // Sample offset
+            }
+
+            let mut labels = b"adtl".to_vec();
+            for (index, (_, label)) in self.markers.iter().enumerate() {
+                let mut body = (index as u32 + 1).to_le_bytes().to_vec();
+                body.extend_from_slice(label.as_bytes());
+                body.push(0);
+                push_chunk(&mut labels, b"labl", &body);
+            }
+            push_chunk(&mut trailer, b"LIST", &labels);
+        }
 
-        // Update headers periodically (every second)
-        if self.last_header_update.elapsed().as_secs() >= 1 {
-            self.update_headers()?;
-            self.last_header_update = Instant::now();
+        let mut info = b"INFO".to_vec();
+        for (id, text) in &self.info {
+            let mut body = text.as_bytes().to_vec();
+            body.push(0);
+            push_chunk(&mut info, id, &body);
         }
+        push_chunk(&mut trailer, b"LIST", &info);
+
+        trailer
+    }
 
+    /// Write the trailing chunks at the end of the sample data, replacing any
+    /// written by an earlier finalize. The write position stays at the data end.
+    fn write_trailer(&mut self) -> io::Result<()> {
+        let data_end = self.writer.stream_position()?;
+        let trailer = self.trailer_chunks();
+        self.writer.write_all(&trailer)?;
+        self.writer.flush()?;
+        self.writer
+            .get_ref()
+            .set_len(data_end + trailer.len() as u64)?;
+        self.writer.seek(SeekFrom::Start(data_end))?;
+        self.trailer_len = trailer.len() as u64;
         Ok(())
     }
 
-    /// Write i16 samples to the WAV file (converting to f32)
-    pub fn write_samples_i16(&mut self, samples: &[i16]) -> io::Result<()> {
-        // Convert i16 to f32 and write
-        for &sample in samples {
-            let f32_sample = sample as f32 / i16::MAX as f32;
-            self.writer.write_all(&f32_sample.to_le_bytes())?;
+    /// Drop the trailing chunks before appending samples over them
+    fn truncate_trailer(&mut self) -> io::Result<()> {
+        if self.trailer_len > 0 {
+            let data_end = self.writer.stream_position()?;
+            self.writer.get_ref().set_len(data_end)?;
+            self.trailer_len = 0;
         }
+        Ok(())
+    }
 
-        self.samples_written += samples.len() as u64;
-
-        // Update headers periodically
-        if self.last_header_update.elapsed().as_secs() >= 1 {
-            self.update_headers()?;
-            self.last_header_update = Instant::now();
+    /// Encode one sample in the file's sample format, little-endian
+    fn write_sample(&mut self, sample: f32) -> io::Result<()> {
+        match self.sample_format {
+            WavSampleFormat::Float32 => self.writer.write_all(&sample.to_le_bytes()),
+            WavSampleFormat::Pcm16 => {
+                let value = (sample.clamp(-1.0, 1.0) * i16::MAX as f32).round() as i16;
+                self.writer.write_all(&value.to_le_bytes())
+            }
+            WavSampleFormat::Pcm24 => {
+                let value = (sample.clamp(-1.0, 1.0) * 8_388_607.0).round() as i32;
+                self.writer.write_all(&value.to_le_bytes()[..3])
+            }
         }
-
-        Ok(())
     }
 
-    /// Write u16 samples to the WAV file (converting to f32)
-    pub fn write_samples_u16(&mut self, samples: &[u16]) -> io::Result<()> {
-        // Convert u16 to f32 and write
+    /// Write f32 samples to the WAV file
+    pub fn write_samples_f32(&mut self, samples: &[f32]) -> io::Result<()> {
+        self.truncate_trailer()?;
         for &sample in samples {
-            let f32_sample = (sample as f32 / u16::MAX as f32) * 2.0 - 1.0;
-            self.writer.write_all(&f32_sample.to_le_bytes())?;
+            self.write_sample(sample)?;
         }
 
         self.samples_written += samples.len() as u64;
 
-        // Update headers periodically
+        // Update headers periodically (every second)
         if self.last_header_update.elapsed().as_secs() >= 1 {
             self.update_headers()?;
             self.last_header_update = Instant::now();
@@ -138,17 +272,32 @@ impl WavWriter {
 
         // Calculate sizes
         let data_size = self.samples_written * self.bytes_per_sample as u64;
-        let file_size = 36 + data_size; // 36 = header size minus RIFF header
+        // Everything after the RIFF size field: header chunks, sample data and trailing chunks
+        let file_size = self.data_chunk_size_pos + 4 - 8 + data_size + self.trailer_len;
 
-        // Update RIFF chunk size
-        self.writer
-            .seek(SeekFrom::Start(self.riff_chunk_size_pos))?;
-        self.writer.write_all(&(file_size as u32).to_le_bytes())?;
+        if file_size > u32::MAX as u64 && !self.is_rf64 {
+            self.switch_to_rf64()?;
+        }
 
-        // Update data chunk size
-        self.writer
-            .seek(SeekFrom::Start(self.data_chunk_size_pos))?;
-        self.writer.write_all(&(data_size as u32).to_le_bytes())?;
+        if self.is_rf64 {
+            // Real sizes live in ds64; the 32-bit fields stay at 0xFFFFFFFF
+            let sample_count = self.samples_written / self.channels as u64;
+            self.writer
+                .seek(SeekFrom::Start(self.ds64_chunk_pos + 8))?;
+            self.writer.write_all(&file_size.to_le_bytes())?;
+            self.writer.write_all(&data_size.to_le_bytes())?;
+            self.writer.write_all(&sample_count.to_le_bytes())?;
+        } else {
+            // Update RIFF chunk size
+            self.writer
+                .seek(SeekFrom::Start(self.riff_chunk_size_pos))?;
+            self.writer.write_all(&(file_size as u32).to_le_bytes())?;
+
+            // Update data chunk size
+            self.writer
+                .seek(SeekFrom::Start(self.data_chunk_size_pos))?;
+            self.writer.write_all(&(data_size as u32).to_le_bytes())?;
+        }
 
         // Seek back to end and flush
         self.writer.seek(SeekFrom::Start(current_pos))?;
@@ -163,8 +312,31 @@ impl WavWriter {
         Ok(())
     }
 
-    /// Finalize the WAV file with correct headers
+    /// Rewrite the header as RF64, turning the reserved JUNK chunk into ds64
+    fn switch_to_rf64(&mut self) -> io::Result<()> {
+        self.writer.seek(SeekFrom::Start(0))?;
+        self.writer.write_all(b"RF64")?;
+        self.writer.write_all(&u32::MAX.to_le_bytes())?;
+
+        self.writer.seek(SeekFrom::Start(self.ds64_chunk_pos))?;
+        self.writer.write_all(b"ds64")?;
+
+        self.writer
+            .seek(SeekFrom::Start(self.data_chunk_size_pos))?;
+        self.writer.write_all(&u32::MAX.to_le_bytes())?;
+
+        self.is_rf64 = true;
+        info!(
+            "WAV file {:?} exceeded 4 GiB, switched to RF64",
+            self.file_path
+        );
+
+        Ok(())
+    }
+
+    /// Finalize the WAV file with correct headers, markers and tags
     pub fn finalize(&mut self) -> io::Result<()> {
+        self.write_trailer()?;
         self.update_headers()?;
         self.writer.flush()?;
 
@@ -199,6 +371,36 @@ impl WavWriter {
     }
 }
 
+/// Append a RIFF chunk, padded to an even size
+fn push_chunk(out: &mut Vec<u8>, id: &[u8; 4], body: &[u8]) {
+    out.extend_from_slice(id);
+    out.extend_from_slice(&(body.len() as u32).to_le_bytes());
+    out.extend_from_slice(body);
+    if body.len() % 2 == 1 {
+        out.push(0);
+    }
+}
+
+/// Today's date as `YYYY-MM-DD` (UTC), the format `ICRD` expects
+fn creation_date() -> String {
+    let days = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs() / 86_400) as i64;
+
+    // Civil date from days since 1970-01-01 (Howard Hinnant's algorithm)
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
 impl Drop for WavWriter {
     fn drop(&mut self) {
         // Ensure headers are updated when the writer is dropped
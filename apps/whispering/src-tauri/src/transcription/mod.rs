@@ -1,13 +1,16 @@
 mod error;
 mod model_manager;
+mod rf64;
 
-use error::TranscriptionError;
+pub use error::TranscriptionError;
 pub use model_manager::ModelManager;
 use log::{debug, error, info, warn};
 use std::io::Write;
 #[cfg(target_os = "windows")]
 use std::os::windows::process::CommandExt;
+use serde::{Deserialize, Serialize};
 use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
 #[cfg(not(target_os = "windows"))]
 use transcribe_rs::engines::moonshine::MoonshineModelParams;
 use transcribe_rs::{
@@ -38,25 +41,12 @@ fn is_valid_wav_format(audio_data: &[u8]) -> bool {
     }
 }
 
-/// Convert audio to whisper-compatible format using pure Rust (no FFmpeg required)
-///
-/// This function converts audio from various formats to 16kHz mono 16-bit PCM WAV.
-/// It handles:
-/// - Channel conversion: stereo → mono (by averaging channels)
-/// - Sample format conversion: any format → f32 → 16-bit PCM
-/// - Sample rate conversion: any Hz → 16kHz using high-quality resampling
-///
-/// This is used as a fallback when FFmpeg is not available, and can handle
-/// most uncompressed WAV formats. For compressed formats (MP3, M4A, etc.),
-/// FFmpeg is still required.
-fn convert_audio_rust(audio_data: Vec<u8>) -> Result<Vec<u8>, TranscriptionError> {
-    debug!(
-        "[Rust Audio Conversion] starting conversion of {} bytes",
-        audio_data.len()
-    );
-
+/// Read a RIFF WAV file with hound and convert its samples to f32
+fn read_wav_samples_f32(
+    audio_data: &[u8],
+) -> Result<(hound::WavSpec, Vec<f32>), TranscriptionError> {
     // Read the input WAV file
-    let cursor = std::io::Cursor::new(&audio_data);
+    let cursor = std::io::Cursor::new(audio_data);
     let mut reader = hound::WavReader::new(cursor).map_err(|e| {
         error!("[Rust Audio Conversion] failed to parse WAV file: {}", e);
         TranscriptionError::AudioReadError {
@@ -65,15 +55,6 @@ fn convert_audio_rust(audio_data: Vec<u8>) -> Result<Vec<u8>, TranscriptionError
     })?;
 
     let spec = reader.spec();
-    let sample_rate = spec.sample_rate;
-    let channels = spec.channels as usize;
-
-    debug!(
-        "[Rust Audio Conversion] input format: {} Hz, {} channels, {} bits, {:?} format",
-        sample_rate, channels, spec.bits_per_sample, spec.sample_format
-    );
-
-    // Step 1: Read all samples and convert to f32 (normalized to [-1.0, 1.0])
     let samples_f32: Vec<f32> = match spec.sample_format {
         hound::SampleFormat::Int => {
             match spec.bits_per_sample {
@@ -115,6 +96,41 @@ fn convert_audio_rust(audio_data: Vec<u8>) -> Result<Vec<u8>, TranscriptionError
         }
     };
 
+    Ok((spec, samples_f32))
+}
+
+/// Convert audio to whisper-compatible format using pure Rust (no FFmpeg required)
+///
+/// This function converts audio from various formats to 16kHz mono 16-bit PCM WAV.
+/// It handles:
+/// - Channel conversion: stereo → mono (by averaging channels)
+/// - Sample format conversion: any format → f32 → 16-bit PCM
+/// - Sample rate conversion: any Hz → 16kHz using high-quality resampling
+///
+/// This is used as a fallback when FFmpeg is not available, and can handle
+/// most uncompressed WAV formats. For compressed formats (MP3, M4A, etc.),
+/// FFmpeg is still required.
+fn convert_audio_rust(audio_data: Vec<u8>) -> Result<Vec<u8>, TranscriptionError> {
+    debug!(
+        "[Rust Audio Conversion] starting conversion of {} bytes",
+        audio_data.len()
+    );
+
+    // Step 1: Read all samples and convert to f32 (normalized to [-1.0, 1.0])
+    // hound can't parse RF64, which the recorder switches to past 4 GiB
+    let (spec, samples_f32) = if rf64::is_rf64(&audio_data) {
+        rf64::read_samples_f32(&audio_data)?
+    } else {
+        read_wav_samples_f32(&audio_data)?
+    };
+    let sample_rate = spec.sample_rate;
+    let channels = spec.channels as usize;
+
+    debug!(
+        "[Rust Audio Conversion] input format: {} Hz, {} channels, {} bits, {:?} format",
+        sample_rate, channels, spec.bits_per_sample, spec.sample_format
+    );
+
     debug!(
         "[Rust Audio Conversion] read {} samples",
         samples_f32.len()
@@ -317,6 +333,16 @@ fn convert_audio_rust(audio_data: Vec<u8>) -> Result<Vec<u8>, TranscriptionError
     Ok(output_bytes)
 }
 
+/// Name of the compressed format the recorder may write (FLAC or Ogg Opus), if
+/// `audio_data` is one. The pure Rust path only decodes WAV, so these need FFmpeg.
+fn compressed_format_name(audio_data: &[u8]) -> Option<&'static str> {
+    match audio_data.get(..4)? {
+        b"fLaC" => Some("FLAC"),
+        b"OggS" => Some("Ogg Opus"),
+        _ => None,
+    }
+}
+
 /// Convert audio to whisper-compatible format (16kHz mono PCM WAV)
 ///
 /// Whisper models require audio in a specific format:
@@ -338,7 +364,8 @@ fn convert_audio_rust(audio_data: Vec<u8>) -> Result<Vec<u8>, TranscriptionError
 /// - Works without FFmpeg installed, making it portable and reliable
 ///
 /// **Tier 3: FFmpeg Conversion (Last Resort)**
-/// - Falls back to FFmpeg for complex formats (MP3, M4A, OGG, etc.)
+/// - Falls back to FFmpeg for complex formats (MP3, M4A, OGG, etc.), including
+///   the recorder's own FLAC and Opus output
 /// - Provides comprehensive format support but requires FFmpeg installation
 /// - Returns `FfmpegNotFoundError` if FFmpeg is not available
 ///
@@ -418,9 +445,11 @@ fn convert_audio_for_whisper(audio_data: Vec<u8>) -> Result<Vec<u8>, Transcripti
         .map_err(|e| {
             // Check if error is specifically "command not found"
             if e.kind() == std::io::ErrorKind::NotFound {
-                TranscriptionError::FfmpegNotFoundError {
-                    message: "FFmpeg is not installed. Install FFmpeg to convert audio formats for local transcription.".to_string(),
-                }
+                let message = match compressed_format_name(&audio_data) {
+                    Some(format) => format!("FFmpeg is not installed. {} recordings can only be transcribed locally with FFmpeg; install it or record as WAV.", format),
+                    None => "FFmpeg is not installed. Install FFmpeg to convert audio formats for local transcription.".to_string(),
+                };
+                TranscriptionError::FfmpegNotFoundError { message }
             } else {
                 TranscriptionError::AudioReadError {
                     message: format!("Failed to run ffmpeg: {}", e),
@@ -486,6 +515,162 @@ fn extract_samples_from_wav(wav_data: Vec<u8>) -> Result<Vec<f32>, Transcription
     Ok(samples)
 }
 
+/// Local engines that can transcribe samples through the `ModelManager`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LocalEngine {
+    Whisper,
+    Parakeet,
+    Moonshine,
+}
+
+impl LocalEngine {
+    fn name(&self) -> &'static str {
+        match self {
+            LocalEngine::Whisper => "Whisper",
+            LocalEngine::Parakeet => "Parakeet",
+            LocalEngine::Moonshine => "Moonshine",
+        }
+    }
+}
+
+/// Extract the Moonshine variant from the model path directory name.
+/// Expected format: moonshine-{variant}-{lang} (e.g., "moonshine-tiny-en", "moonshine-base-en")
+#[cfg(not(target_os = "windows"))]
+fn moonshine_params(model_path: &str) -> MoonshineModelParams {
+    let dir_name = std::path::Path::new(model_path)
+        .file_name()
+        .and_then(|n| n.to_str())
+        .unwrap_or("");
+
+    // Parse directory name: moonshine-{variant}-{lang}
+    let parts: Vec<&str> = dir_name.split('-').collect();
+    let variant = parts.get(1).copied().unwrap_or("tiny");
+
+    debug!(
+        "[Transcription] extracted Moonshine variant='{}' from path '{}'",
+        variant, dir_name
+    );
+
+    match variant {
+        "base" => MoonshineModelParams::base(),
+        "tiny" => MoonshineModelParams::tiny(),
+        _ => {
+            warn!(
+                "[Transcription] unknown Moonshine variant '{}' in path '{}', defaulting to tiny",
+                variant, dir_name
+            );
+            MoonshineModelParams::tiny()
+        }
+    }
+}
+
+#[cfg(not(target_os = "windows"))]
+fn load_moonshine(
+    model_manager: &ModelManager,
+    model_path: &str,
+) -> Result<Arc<Mutex<Option<model_manager::Engine>>>, String> {
+    model_manager.get_or_load_moonshine(PathBuf::from(model_path), moonshine_params(model_path))
+}
+
+#[cfg(target_os = "windows")]
+fn load_moonshine(
+    model_manager: &ModelManager,
+    model_path: &str,
+) -> Result<Arc<Mutex<Option<model_manager::Engine>>>, String> {
+    model_manager.get_or_load_moonshine(PathBuf::from(model_path), "")
+}
+
+/// Transcribe 16kHz mono samples with a local engine, loading the model through
+/// the persistent model manager if needed. `language` and `initial_prompt` only
+/// apply to Whisper.
+pub fn transcribe_samples(
+    model_manager: &ModelManager,
+    engine: LocalEngine,
+    model_path: &str,
+    language: Option<String>,
+    initial_prompt: Option<String>,
+    samples: Vec<f32>,
+) -> Result<String, TranscriptionError> {
+    // Get or load the model using the persistent model manager
+    let engine_arc = match engine {
+        LocalEngine::Whisper => model_manager.get_or_load_whisper(PathBuf::from(model_path)),
+        LocalEngine::Parakeet => model_manager.get_or_load_parakeet(PathBuf::from(model_path)),
+        LocalEngine::Moonshine => load_moonshine(model_manager, model_path),
+    }
+    .map_err(|e| TranscriptionError::ModelLoadError { message: e })?;
+    debug!("[Transcription] {} model ready: {}", engine.name(), model_path);
+
+    // Run transcription with the persistent engine
+    // Use into_inner() to recover from poisoned mutex, but clear state to force fresh reload
+    let mut engine_guard = engine_arc.lock().unwrap_or_else(|poisoned| {
+        warn!(
+            "[Transcription] Engine mutex was poisoned from previous panic, clearing state to force reload..."
+        );
+        let mut recovered = poisoned.into_inner();
+        *recovered = None; // Clear potentially corrupted state
+        recovered
+    });
+    let loaded = engine_guard
+        .as_mut()
+        .ok_or_else(|| TranscriptionError::ModelLoadError {
+            message: "Model not loaded (may have been cleared after previous error). Please try again.".to_string(),
+        })?;
+
+    let result = match (engine, loaded) {
+        #[cfg(not(target_os = "windows"))]
+        (LocalEngine::Whisper, model_manager::Engine::Whisper(whisper_engine)) => {
+            // Configure inference parameters
+            let mut params = WhisperInferenceParams::default();
+            params.language = language;
+            params.initial_prompt = initial_prompt;
+            params.print_special = false;
+            params.print_progress = false;
+            params.print_realtime = false;
+            params.print_timestamps = false;
+            params.suppress_blank = true;
+            params.suppress_non_speech_tokens = true;
+            params.no_speech_thold = 0.2;
+
+            whisper_engine
+                .transcribe_samples(samples, Some(params))
+                .map(|result| result.text)
+                .map_err(|e| e.to_string())
+        }
+        (LocalEngine::Parakeet, model_manager::Engine::Parakeet(parakeet_engine)) => {
+            let params = ParakeetInferenceParams {
+                timestamp_granularity: TimestampGranularity::Segment,
+                ..Default::default()
+            };
+
+            parakeet_engine
+                .transcribe_samples(samples, Some(params))
+                .map(|result| result.text)
+                .map_err(|e| e.to_string())
+        }
+        #[cfg(not(target_os = "windows"))]
+        (LocalEngine::Moonshine, model_manager::Engine::Moonshine(moonshine_engine)) => {
+            // Moonshine doesn't have inference params like Whisper, pass None
+            moonshine_engine
+                .transcribe_samples(samples, None)
+                .map(|result| result.text)
+                .map_err(|e| e.to_string())
+        }
+        _ => {
+            return Err(TranscriptionError::ModelLoadError {
+                message: format!("Expected {} engine but got different type", engine.name()),
+            })
+        }
+    };
+
+    #[cfg(target_os = "windows")]
+    let _ = (language, initial_prompt);
+
+    result
+        .map(|text| text.trim().to_string())
+        .map_err(|message| TranscriptionError::TranscriptionError { message })
+}
+
 #[cfg(not(target_os = "windows"))]
 #[tauri::command]
 pub async fn transcribe_audio_whisper(
@@ -521,59 +706,14 @@ pub async fn transcribe_audio_whisper(
         return Ok(String::new());
     }
 
-    // Get or load the model using the persistent model manager
-    let engine_arc = model_manager
-        .get_or_load_whisper(PathBuf::from(&model_path))
-        .map_err(|e| TranscriptionError::ModelLoadError { message: e })?;
-    debug!("[Transcription] Whisper model ready: {}", model_path);
-
-    // Configure inference parameters
-    let mut params = WhisperInferenceParams::default();
-    params.language = language;
-    params.initial_prompt = initial_prompt;
-    params.print_special = false;
-    params.print_progress = false;
-    params.print_realtime = false;
-    params.print_timestamps = false;
-    params.suppress_blank = true;
-    params.suppress_non_speech_tokens = true;
-    params.no_speech_thold = 0.2;
-
-    // Run transcription with the persistent engine
-    // Use into_inner() to recover from poisoned mutex, but clear state to force fresh reload
-    let result = {
-        let mut engine_guard = engine_arc.lock().unwrap_or_else(|poisoned| {
-            warn!(
-                "[Transcription] Engine mutex was poisoned from previous panic, clearing state to force reload..."
-            );
-            let mut recovered = poisoned.into_inner();
-            *recovered = None; // Clear potentially corrupted state
-            recovered
-        });
-        let engine = engine_guard
-            .as_mut()
-            .ok_or_else(|| TranscriptionError::ModelLoadError {
-                message: "Model not loaded (may have been cleared after previous error). Please try again.".to_string(),
-            })?;
-
-        // Extract the WhisperEngine from the enum
-        let whisper_engine = match engine {
-            model_manager::Engine::Whisper(e) => e,
-            _ => {
-                return Err(TranscriptionError::ModelLoadError {
-                    message: "Expected Whisper engine but got different type".to_string(),
-                })
-            }
-        };
-
-        whisper_engine
-            .transcribe_samples(samples, Some(params))
-            .map_err(|e| TranscriptionError::TranscriptionError {
-                message: e.to_string(),
-            })?
-    };
-
-    let transcript = result.text.trim().to_string();
+    let transcript = transcribe_samples(
+        &model_manager,
+        LocalEngine::Whisper,
+        &model_path,
+        language,
+        initial_prompt,
+        samples,
+    )?;
     info!(
         "[Transcription] Whisper transcription complete: characters={}",
         transcript.len()
@@ -627,52 +767,14 @@ pub async fn transcribe_audio_parakeet(
         return Ok(String::new());
     }
 
-    // Get or load the model using the persistent model manager
-    let engine_arc = model_manager
-        .get_or_load_parakeet(PathBuf::from(&model_path))
-        .map_err(|e| TranscriptionError::ModelLoadError { message: e })?;
-    debug!("[Transcription] Parakeet model ready: {}", model_path);
-
-    let params = ParakeetInferenceParams {
-        timestamp_granularity: TimestampGranularity::Segment,
-        ..Default::default()
-    };
-
-    // Run transcription with the persistent engine
-    // Use into_inner() to recover from poisoned mutex, but clear state to force fresh reload
-    let result = {
-        let mut engine_guard = engine_arc.lock().unwrap_or_else(|poisoned| {
-            warn!(
-                "[Transcription] Engine mutex was poisoned from previous panic, clearing state to force reload..."
-            );
-            let mut recovered = poisoned.into_inner();
-            *recovered = None; // Clear potentially corrupted state
-            recovered
-        });
-        let engine = engine_guard
-            .as_mut()
-            .ok_or_else(|| TranscriptionError::ModelLoadError {
-                message: "Model not loaded (may have been cleared after previous error). Please try again.".to_string(),
-            })?;
-
-        // Extract the ParakeetEngine from the enum
-        let parakeet_engine = match engine {
-            model_manager::Engine::Parakeet(e) => e,
-            _ => {
-                return Err(TranscriptionError::ModelLoadError {
-                    message: "Expected Parakeet engine but got different type".to_string(),
-                })
-            }
-        };
-
-        parakeet_engine
-            .transcribe_samples(samples, Some(params))
-            .map_err(|e| TranscriptionError::TranscriptionError {
-                message: e.to_string(),
-            })?
-    };
-
-    let transcript = result.text.trim().to_string();
+    let transcript = transcribe_samples(
+        &model_manager,
+        LocalEngine::Parakeet,
+        &model_path,
+        None,
+        None,
+        samples,
+    )?;
     info!(
         "[Transcription] Parakeet transcription complete: characters={}",
         transcript.len()
@@ -713,78 +815,14 @@ pub async fn transcribe_audio_moonshine(
         return Ok(String::new());
     }
 
-    // Extract variant from model path directory name
-    // Expected format: moonshine-{variant}-{lang} (e.g., "moonshine-tiny-en", "moonshine-base-en")
-    let model_params = {
-        let dir_name = std::path::Path::new(&model_path)
-            .file_name()
-            .and_then(|n| n.to_str())
-            .unwrap_or("");
-
-        // Parse directory name: moonshine-{variant}-{lang}
-        let parts: Vec<&str> = dir_name.split('-').collect();
-        let variant = parts.get(1).copied().unwrap_or("tiny");
-
-        debug!(
-            "[Transcription] extracted Moonshine variant='{}' from path '{}'",
-            variant, dir_name
-        );
-
-        match variant {
-            "base" => MoonshineModelParams::base(),
-            "tiny" => MoonshineModelParams::tiny(),
-            _ => {
-                warn!(
-                    "[Transcription] unknown Moonshine variant '{}' in path '{}', defaulting to tiny",
-                    variant, dir_name
-                );
-                MoonshineModelParams::tiny()
-            }
-        }
-    };
-
-    // Get or load the model using the persistent model manager
-    let engine_arc = model_manager
-        .get_or_load_moonshine(PathBuf::from(&model_path), model_params)
-        .map_err(|e| TranscriptionError::ModelLoadError { message: e })?;
-    debug!("[Transcription] Moonshine model ready: {}", model_path);
-
-    // Run transcription with the persistent engine
-    // Use into_inner() to recover from poisoned mutex, but clear state to force fresh reload
-    let result = {
-        let mut engine_guard = engine_arc.lock().unwrap_or_else(|poisoned| {
-            warn!(
-                "[Transcription] Engine mutex was poisoned from previous panic, clearing state to force reload..."
-            );
-            let mut recovered = poisoned.into_inner();
-            *recovered = None; // Clear potentially corrupted state
-            recovered
-        });
-        let engine = engine_guard
-            .as_mut()
-            .ok_or_else(|| TranscriptionError::ModelLoadError {
-                message: "Model not loaded (may have been cleared after previous error). Please try again.".to_string(),
-            })?;
-
-        // Extract the MoonshineEngine from the enum
-        let moonshine_engine = match engine {
-            model_manager::Engine::Moonshine(e) => e,
-            _ => {
-                return Err(TranscriptionError::ModelLoadError {
-                    message: "Expected Moonshine engine but got different type".to_string(),
-                })
-            }
-        };
-
-        // Moonshine doesn't have inference params like Whisper, pass None
-        moonshine_engine
-            .transcribe_samples(samples, None)
-            .map_err(|e| TranscriptionError::TranscriptionError {
-                message: e.to_string(),
-            })?
-    };
-
-    let transcript = result.text.trim().to_string();
+    let transcript = transcribe_samples(
+        &model_manager,
+        LocalEngine::Moonshine,
+        &model_path,
+        None,
+        None,
+        samples,
+    )?;
     info!(
         "[Transcription] Moonshine transcription complete: characters={}",
         transcript.len()
@@ -0,0 +1,170 @@
+use super::error::TranscriptionError;
+use log::debug;
+
+/// WAVE_FORMAT_PCM
+const FORMAT_PCM: u16 = 1;
+/// WAVE_FORMAT_IEEE_FLOAT
+const FORMAT_IEEE_FLOAT: u16 = 3;
+/// WAVE_FORMAT_EXTENSIBLE - the real format is the first two bytes of the sub-format GUID
+const FORMAT_EXTENSIBLE: u16 = 0xFFFE;
+
+/// Check whether the data is an RF64/BW64 file (WAV with 64-bit sizes, EBU Tech 3306)
+pub fn is_rf64(audio_data: &[u8]) -> bool {
+    audio_data.len() >= 12
+        && (&audio_data[0..4] == b"RF64" || &audio_data[0..4] == b"BW64")
+        && &audio_data[8..12] == b"WAVE"
+}
+
+/// Parse an RF64 file and convert its samples to f32.
+///
+/// hound only understands 32-bit RIFF sizes, so recordings that outgrew 4 GiB
+/// are read here instead. The `ds64` chunk supplies the real data size; if the
+/// file was cut short (e.g. by a crash), whatever data is present is used.
+pub fn read_samples_f32(
+    audio_data: &[u8],
+) -> Result<(hound::WavSpec, Vec<f32>), TranscriptionError> {
+    let mut ds64_data_size: Option<u64> = None;
+    let mut spec: Option<hound::WavSpec> = None;
+    let mut pos = 12;
+
+    while pos + 8 <= audio_data.len() {
+        let id = &audio_data[pos..pos + 4];
+        let size = read_u32(audio_data, pos + 4) as u64;
+        let body = pos + 8;
+
+        match id {
+            b"ds64" => {
+                if body + 16 > audio_data.len() {
+                    return Err(read_error("Truncated ds64 chunk"));
+                }
+                ds64_data_size = Some(read_u64(audio_data, body + 8));
+            }
+            b"fmt " => {
+                if body + 16 > audio_data.len() {
+                    return Err(read_error("Truncated fmt chunk"));
+                }
+                spec = Some(parse_fmt(audio_data, body, size)?);
+            }
+            b"data" => {
+                let spec = spec.ok_or_else(|| read_error("data chunk before fmt chunk"))?;
+                let declared = if size == u32::MAX as u64 {
+                    ds64_data_size.ok_or_else(|| read_error("Missing ds64 chunk"))?
+                } else {
+                    size
+                };
+                let available = (audio_data.len() - body) as u64;
+                if declared > available {
+                    debug!(
+                        "[RF64] data chunk declares {} bytes but only {} are present",
+                        declared, available
+                    );
+                }
+                let data = &audio_data[body..body + declared.min(available) as usize];
+                let samples = decode_samples(data, &spec)?;
+
+                debug!(
+                    "[RF64] read {} samples: {} Hz, {} channels, {} bits, {:?} format",
+                    samples.len(),
+                    spec.sample_rate,
+                    spec.channels,
+                    spec.bits_per_sample,
+                    spec.sample_format
+                );
+
+                return Ok((spec, samples));
+            }
+            _ => {}
+        }
+
+        // Chunks are padded to an even size
+        pos = body.saturating_add((size + (size & 1)) as usize);
+    }
+
+    Err(read_error("RF64 file has no data chunk"))
+}
+
+fn parse_fmt(
+    audio_data: &[u8],
+    body: usize,
+    size: u64,
+) -> Result<hound::WavSpec, TranscriptionError> {
+    let mut format_tag = read_u16(audio_data, body);
+    let channels = read_u16(audio_data, body + 2);
+    let sample_rate = read_u32(audio_data, body + 4);
+    let bits_per_sample = read_u16(audio_data, body + 14);
+
+    if format_tag == FORMAT_EXTENSIBLE && size >= 40 && body + 26 <= audio_data.len() {
+        format_tag = read_u16(audio_data, body + 24);
+    }
+
+    let sample_format = match format_tag {
+        FORMAT_PCM => hound::SampleFormat::Int,
+        FORMAT_IEEE_FLOAT => hound::SampleFormat::Float,
+        other => {
+            return Err(read_error(&format!(
+                "Unsupported RF64 format tag: {:#06x}",
+                other
+            )))
+        }
+    };
+
+    if channels == 0 {
+        return Err(read_error("RF64 file declares zero channels"));
+    }
+
+    Ok(hound::WavSpec {
+        channels,
+        sample_rate,
+        bits_per_sample,
+        sample_format,
+    })
+}
+
+fn decode_samples(data: &[u8], spec: &hound::WavSpec) -> Result<Vec<f32>, TranscriptionError> {
+    let samples = match (spec.sample_format, spec.bits_per_sample) {
+        (hound::SampleFormat::Int, 16) => data
+            .chunks_exact(2)
+            .map(|b| i16::from_le_bytes([b[0], b[1]]) as f32 / 32768.0)
+            .collect(),
+        (hound::SampleFormat::Int, 24) => data
+            .chunks_exact(3)
+            .map(|b| (i32::from_le_bytes([0, b[0], b[1], b[2]]) >> 8) as f32 / 8388608.0)
+            .collect(),
+        (hound::SampleFormat::Int, 32) => data
+            .chunks_exact(4)
+            .map(|b| i32::from_le_bytes([b[0], b[1], b[2], b[3]]) as f32 / 2147483648.0)
+            .collect(),
+        (hound::SampleFormat::Float, 32) => data
+            .chunks_exact(4)
+            .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+            .collect(),
+        (format, bits) => {
+            return Err(read_error(&format!(
+                "Unsupported RF64 sample format: {}-bit {:?}",
+                bits, format
+            )))
+        }
+    };
+
+    Ok(samples)
+}
+
+fn read_u16(data: &[u8], pos: usize) -> u16 {
+    u16::from_le_bytes([data[pos], data[pos + 1]])
+}
+
+fn read_u32(data: &[u8], pos: usize) -> u32 {
+    u32::from_le_bytes([data[pos], data[pos + 1], data[pos + 2], data[pos + 3]])
+}
+
+fn read_u64(data: &[u8], pos: usize) -> u64 {
+    let mut bytes = [0u8; 8];
+    bytes.copy_from_slice(&data[pos..pos + 8]);
+    u64::from_le_bytes(bytes)
+}
+
+fn read_error(message: &str) -> TranscriptionError {
+    TranscriptionError::AudioReadError {
+        message: format!("Failed to parse RF64 file: {}", message),
+    }
+}
@@ -74,6 +74,8 @@ export const PATHS = {
 		 * Each recording consists of two files sharing the same ID:
 		 * - {id}.md: Markdown file with YAML frontmatter (metadata) and body (transcribed text)
 		 * - {id}.{ext}: Audio file (extension depends on recording format: webm, mp3, wav, etc.)
+		 * The desktop recorder keeps extra files, such as its metadata sidecar, in
+		 * subfolders so they are never mistaken for the audio.
 		 */
 
 		/**
@@ -1,6 +1,5 @@
 import { invoke as tauriInvoke } from '@tauri-apps/api/core';
-import { remove } from '@tauri-apps/plugin-fs';
-import { extractErrorMessage } from 'wellcrafted/error';
+import { type } from 'arktype';
 import { Err, Ok, type Result, tryAsync } from 'wellcrafted/result';
 import type {
 	CancelRecordingResult,
@@ -26,7 +25,131 @@ type AudioRecording = {
 	sampleRate: number;
 	channels: number;
 	durationSeconds: number;
+	pausedSeconds: number;
+	overrunCount: number;
+	droppedSamples: number;
+	/** Set when the session captured a second source */
+	secondaryOverrunCount: number | null;
+	secondaryDroppedSamples: number | null;
+	format: 'wav' | 'flac' | 'opus';
+	fileExtension: string;
 	filePath?: string;
+	rawFilePath?: string;
+	transcript?: string;
+	markers: RecordingMarker[];
+	quality: RecordingQuality | null;
+	segments: RecordingSegment[];
+};
+
+/**
+ * Levels, clipping and silence of the written audio, measured while recording
+ */
+type RecordingQuality = {
+	peak: number;
+	peakDb: number;
+	rmsDb: number;
+	loudnessLufs: number | null;
+	clippedSamples: number;
+	dcOffset: number;
+	silenceRatio: number;
+};
+
+/**
+ * One file of a recording, in order; sessions with file rotation have several
+ */
+type RecordingSegment = {
+	index: number;
+	filePath: string;
+	durationSeconds: number;
+};
+
+/**
+ * Moment flagged with `add_recording_marker`; embedded as a cue point in WAV files
+ */
+type RecordingMarker = {
+	id: number;
+	label: string;
+	frame: number;
+	seconds: number;
+};
+
+/**
+ * Recorder session status returned from the Rust method
+ */
+type RecorderStatus = {
+	recordingId: string | null;
+	phase:
+		| 'idle'
+		| 'sessionReady'
+		| 'recording'
+		| 'paused'
+		| 'finalizing'
+		| 'error';
+	deviceId: string | null;
+	elapsedSeconds: number;
+	bytesWritten: number;
+	diskSpace: DiskSpace | null;
+	error: string | null;
+};
+
+/**
+ * Free space where a session writes, returned by `init_recording_session` and in the status
+ */
+type DiskSpace = {
+	availableBytes: number;
+	bytesPerSecond: number;
+	estimatedMinutesRemaining: number;
+};
+
+/**
+ * Error returned from the Rust recorder methods, tagged by `name`
+ */
+const RecorderErrorType = type({
+	name: "'DeviceNotFound' | 'UnsupportedConfig' | 'StreamBuildFailed' | 'PermissionDenied' | 'DiskWrite' | 'FileRead' | 'NoSession' | 'InvalidState' | 'Processing' | 'Internal'",
+	message: 'string',
+});
+
+/**
+ * Explains why a recording session couldn't be set up, based on the Rust error
+ */
+const describeInitError = (unknownError: unknown): string => {
+	const error = RecorderErrorType(unknownError);
+	if (error instanceof type.errors)
+		return 'We encountered an issue while setting up your recording session. This could be because your microphone is being used by another app, your microphone permissions are denied, or the selected recording device is disconnected';
+
+	switch (error.name) {
+		case 'DeviceNotFound':
+			return "We couldn't find the selected microphone. Make sure it's connected and try again!";
+		case 'PermissionDenied':
+			return `Microphone or file access was denied. Check your system permissions and try again. (${error.message})`;
+		case 'UnsupportedConfig':
+			return `Your microphone doesn't support a usable recording format. (${error.message})`;
+		case 'DiskWrite':
+			return `We couldn't create the recording file. Check that the recordings folder exists and has free space. (${error.message})`;
+		default:
+			return `We encountered an issue while setting up your recording session. This could be because your microphone is being used by another app. (${error.message})`;
+	}
+};
+
+/**
+ * Recording device returned from the Rust method, with a stable identifier
+ */
+type RecordingDevice = {
+	id: string;
+	name: string;
+	isDefault: boolean;
+	isMonitor: boolean;
+	host: string;
+	channelCounts: number[];
+	sampleFormats: string[];
+	supportedConfigs: {
+		channels: number;
+		minSampleRate: number;
+		maxSampleRate: number;
+		sampleFormat: string;
+	}[];
+	defaultSampleRate: number | null;
+	defaultChannels: number | null;
 };
 
 /**
@@ -35,18 +158,17 @@ type AudioRecording = {
 const enumerateDevices = async (): Promise<
 	Result<Device[], RecorderServiceError>
 > => {
-	const { data: deviceNames, error: enumerateRecordingDevicesError } =
-		await invoke<string[]>('enumerate_recording_devices');
+	const { data: recordingDevices, error: enumerateRecordingDevicesError } =
+		await invoke<RecordingDevice[]>('enumerate_recording_devices');
 	if (enumerateRecordingDevicesError) {
 		return RecorderServiceErr({
 			message: 'Failed to enumerate recording devices',
 		});
 	}
-	// On desktop, device names serve as both ID and label
 	return Ok(
-		deviceNames.map((name) => ({
-			id: asDeviceIdentifier(name),
-			label: name,
+		recordingDevices.map((device) => ({
+			id: asDeviceIdentifier(device.id),
+			label: device.name,
 		})),
 	);
 };
@@ -56,6 +178,25 @@ const enumerateDevices = async (): Promise<
  * This service handles device enumeration, recording start/stop operations, and file management
  * for desktop audio recording using the CPAL library.
  */
+/**
+ * Recording ID of the session this service started. The backend can run
+ * several sessions at once, so every command names the one it targets.
+ */
+let activeRecordingId: string | null = null;
+
+/**
+ * Checks whether this service's session is currently recording (or paused)
+ */
+const isSessionActive = async () => {
+	if (activeRecordingId === null) return Ok(false);
+	const { data: status, error } = await invoke<RecorderStatus>(
+		'get_recorder_status',
+		{ recordingId: activeRecordingId },
+	);
+	if (error) return Err(error);
+	return Ok(status.phase === 'recording' || status.phase === 'paused');
+};
+
 export const CpalRecorderServiceLive: RecorderService = {
 	/**
 	 * Gets the current state of the recorder.
@@ -63,16 +204,15 @@ export const CpalRecorderServiceLive: RecorderService = {
 	getRecorderState: async (): Promise<
 		Result<WhisperingRecordingState, RecorderServiceError>
 	> => {
-		const { data: recordingId, error: getRecorderStateError } = await invoke<
-			string | null
-		>('get_current_recording_id');
+		const { data: isActive, error: getRecorderStateError } =
+			await isSessionActive();
 		if (getRecorderStateError)
 			return RecorderServiceErr({
 				message:
 					'We encountered an issue while getting the recorder state. This could be because your microphone is being used by another app, your microphone permissions are denied, or the selected recording device is disconnected',
 			});
 
-		return Ok(recordingId ? 'RECORDING' : 'IDLE');
+		return Ok(isActive ? 'RECORDING' : 'IDLE');
 	},
 
 	enumerateDevices,
@@ -126,11 +266,14 @@ export const CpalRecorderServiceLive: RecorderService = {
 				});
 			}
 
-			// Check if the selected device exists in the devices array
-			const deviceExists = deviceIds.includes(selectedDeviceId);
+			// Check if the selected device exists in the devices array.
+			// Selections saved before stable IDs existed hold the device name.
+			const selectedDevice =
+				devices.find((d) => d.id === selectedDeviceId) ??
+				devices.find((d) => d.label === selectedDeviceId);
 
-			if (deviceExists)
-				return Ok({ outcome: 'success', deviceId: selectedDeviceId });
+			if (selectedDevice)
+				return Ok({ outcome: 'success', deviceId: selectedDevice.id });
 
 			sendStatus({
 				title: '⚠️ Finding a New Microphone',
@@ -174,17 +317,19 @@ export const CpalRecorderServiceLive: RecorderService = {
 		);
 		if (initRecordingSessionError)
 			return RecorderServiceErr({
-				message:
-					'We encountered an issue while setting up your recording session. This could be because your microphone is being used by another app, your microphone permissions are denied, or the selected recording device is disconnected',
+				message: describeInitError(initRecordingSessionError.error),
 			});
+		activeRecordingId = recordingId;
 
 		sendStatus({
 			title: '🎙️ Starting Recording',
 			description:
 				'Recording session initialized, now starting to capture audio...',
 		});
-		const { error: startRecordingError } =
-			await invoke<void>('start_recording');
+		const { error: startRecordingError } = await invoke<void>(
+			'start_recording',
+			{ recordingId },
+		);
 		if (startRecordingError)
 			return RecorderServiceErr({
 				message:
@@ -203,15 +348,22 @@ export const CpalRecorderServiceLive: RecorderService = {
 	stopRecording: async ({
 		sendStatus,
 	}): Promise<Result<Blob, RecorderServiceError>> => {
+		const recordingId = activeRecordingId;
+		if (!recordingId) {
+			return RecorderServiceErr({
+				message: 'There is no recording to stop.',
+			});
+		}
+
 		const { data: audioRecording, error: stopRecordingError } =
-			await invoke<AudioRecording>('stop_recording');
+			await invoke<AudioRecording>('stop_recording', { recordingId });
 		if (stopRecordingError) {
 			return RecorderServiceErr({
 				message: 'Unable to save your recording. Please try again.',
 			});
 		}
 
-		const { filePath } = audioRecording;
+		const { filePath, segments } = audioRecording;
 		// Desktop recorder should always write to a file
 		if (!filePath) {
 			return RecorderServiceErr({
@@ -220,6 +372,17 @@ export const CpalRecorderServiceLive: RecorderService = {
 		}
 		// audioRecording is now AudioRecordingWithFile
 
+		// A rotated recording spans several files, but the recorder service hands
+		// back a single Blob. Refuse rather than silently keeping only the first
+		// segment; the files stay on disk. This session never enables rotation.
+		if (segments.length > 1) {
+			await invoke<void>('close_recording_session', { recordingId });
+			activeRecordingId = null;
+			return RecorderServiceErr({
+				message: `Recording was split into ${segments.length} files, which can't be loaded as one recording yet. The files are saved at: ${segments.map((segment) => segment.filePath).join(', ')}`,
+			});
+		}
+
 		// Read the WAV file from disk
 		sendStatus({
 			title: '📁 Reading Recording',
@@ -237,7 +400,11 @@ export const CpalRecorderServiceLive: RecorderService = {
 			title: '🔄 Closing Session',
 			description: 'Cleaning up recording resources...',
 		});
-		const { error: closeError } = await invoke<void>('close_recording_session');
+		const { error: closeError } = await invoke<void>(
+			'close_recording_session',
+			{ recordingId },
+		);
+		activeRecordingId = null;
 		if (closeError) {
 			// Log but don't fail the stop operation
 			console.error('Failed to close recording session:', closeError);
@@ -256,9 +423,9 @@ export const CpalRecorderServiceLive: RecorderService = {
 		sendStatus,
 	}): Promise<Result<CancelRecordingResult, RecorderServiceError>> => {
 		// Check current state first
-		const { data: recordingId, error: getRecordingIdError } = await invoke<
-			string | null
-		>('get_current_recording_id');
+		const recordingId = activeRecordingId;
+		const { data: isActive, error: getRecordingIdError } =
+			await isSessionActive();
 		if (getRecordingIdError) {
 			return RecorderServiceErr({
 				message:
@@ -266,7 +433,7 @@ export const CpalRecorderServiceLive: RecorderService = {
 			});
 		}
 
-		if (!recordingId) {
+		if (!recordingId || !isActive) {
 			return Ok({ status: 'no-recording' });
 		}
 
@@ -276,37 +443,16 @@ export const CpalRecorderServiceLive: RecorderService = {
 				'Safely stopping your recording and cleaning up resources...',
 		});
 
-		// First get the recording data to know if there's a file to delete
-		const { data: audioRecording } =
-			await invoke<AudioRecording>('stop_recording');
-
-		// If there's a file path, delete the file using Tauri FS plugin
-		if (audioRecording?.filePath) {
-			const { filePath } = audioRecording;
-			const { error: removeError } = await tryAsync({
-				try: () => remove(filePath),
-				catch: (error) =>
-					RecorderServiceErr({
-						message: `Failed to delete recording file: ${extractErrorMessage(error)}`,
-					}),
-			});
-			if (removeError)
-				sendStatus({
-					title: '❌ Error Deleting Recording File',
-					description:
-						"We couldn't delete the recording file. Continuing with the cancellation process...",
-				});
-		}
-
-		// Close the recording session after cancelling
-		sendStatus({
-			title: '🔄 Closing Session',
-			description: 'Cleaning up recording resources...',
+		// Stops the recording, deletes its file and sidecar, and closes the session
+		const { error: cancelError } = await invoke<void>('cancel_recording', {
+			recordingId,
 		});
-		const { error: closeError } = await invoke<void>('close_recording_session');
-		if (closeError) {
-			// Log but don't fail the cancel operation
-			console.error('Failed to close recording session:', closeError);
+		activeRecordingId = null;
+		if (cancelError) {
+			return RecorderServiceErr({
+				message:
+					'Unable to cancel the recording. Please try closing the app and starting again.',
+			});
 		}
 
 		return Ok({ status: 'cancelled' });
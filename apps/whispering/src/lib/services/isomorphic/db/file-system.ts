@@ -82,6 +82,37 @@ async function bulkDeleteFiles(paths: string[]): Promise<number> {
 	return invoke('bulk_delete_files', { paths });
 }
 
+/**
+ * Subfolders of the recordings folder where the desktop recorder keeps files
+ * that belong to a recording without being its audio, named by recording ID.
+ * They sit below the top level so `findAudioFile` never serves one as the audio.
+ */
+const RECORDING_SIDE_FOLDERS = ['metadata', 'raw', 'segments'] as const;
+
+/**
+ * Find the files in the recording side folders, limited to the recordings in
+ * `ids` when given.
+ */
+async function findSideFiles(
+	recordingsPath: string,
+	ids?: Set<string>,
+): Promise<string[]> {
+	const { join } = await import('@tauri-apps/api/path');
+	const paths: string[] = [];
+	for (const folder of RECORDING_SIDE_FOLDERS) {
+		const folderPath = await join(recordingsPath, folder);
+		if (!(await exists(folderPath))) continue;
+		for (const file of await readDir(folderPath)) {
+			// Extract ID from filename (everything before the first dot)
+			const id = file.name.split('.')[0] ?? '';
+			if (file.isFile && (!ids || ids.has(id))) {
+				paths.push(await join(folderPath, file.name));
+			}
+		}
+	}
+	return paths;
+}
+
 /**
  * File system-based database implementation for desktop.
  * Stores data as markdown files with YAML front matter.
@@ -90,6 +121,10 @@ async function bulkDeleteFiles(paths: string[]): Promise<number> {
  * - recordings/
  *   - {id}.md (metadata with YAML front matter + transcribed text)
  *   - {id}.{ext} (audio file: .wav, .opus, .mp3, etc.)
+ *   - metadata/{id}.json (recorder sidecar, desktop recorder only)
+ *   - raw/{id}.{ext} (unprocessed take, when the desktop recorder keeps one)
+ *   - segments/{id}.NNN.{ext} (later files of a recording the desktop recorder
+ *     rotated; {id}.{ext} is the first and the sidecar lists them all in order)
  * - transformations/
  *   - {id}.md (transformation configuration)
  * - transformation-runs/
@@ -310,6 +345,9 @@ export function createFileSystemDb(): DbService {
 								})
 								.map((file) => PATHS.DB.RECORDING_FILE(file.name)),
 						);
+						pathsToDelete.push(
+							...(await findSideFiles(recordingsPath, idsToDelete)),
+						);
 
 						// Single FFI call to delete all files in parallel
 						await bulkDeleteFiles(pathsToDelete);
@@ -424,6 +462,7 @@ export function createFileSystemDb(): DbService {
 						const pathsToDelete = await Promise.all(
 							files.map((file) => PATHS.DB.RECORDING_FILE(file.name)),
 						);
+						pathsToDelete.push(...(await findSideFiles(recordingsPath)));
 
 						// Single FFI call to delete all files in parallel
 						await bulkDeleteFiles(pathsToDelete);
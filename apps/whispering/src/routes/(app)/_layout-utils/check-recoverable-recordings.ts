@@ -0,0 +1,78 @@
+import { invoke } from '@tauri-apps/api/core';
+import { extractErrorMessage } from 'wellcrafted/error';
+import { PATHS } from '$lib/constants/paths';
+import { rpc } from '$lib/query';
+import { settings } from '$lib/stores/settings.svelte';
+
+/**
+ * WAV file left unfinalized by a crash, as returned by `list_recoverable_recordings`
+ */
+type RecoverableRecording = {
+	recordingId: string;
+	filePath: string;
+	sampleRate: number;
+	channels: number;
+	durationSeconds: number;
+	fileSizeBytes: number;
+	modifiedAtMs: number | null;
+};
+
+/**
+ * Scans the CPAL output folder for recordings a crash or power loss left with
+ * stale WAV headers, and offers to repair them so they play back in full.
+ */
+export async function checkRecoverableRecordings() {
+	if (!window.__TAURI_INTERNALS__) return;
+
+	try {
+		const outputFolder =
+			settings.value['recording.cpal.outputFolder'] ??
+			(await PATHS.DB.RECORDINGS());
+		const recordings = await invoke<RecoverableRecording[]>(
+			'list_recoverable_recordings',
+			{ outputFolder },
+		);
+		if (recordings.length === 0) return;
+
+		const totalMinutes =
+			recordings.reduce((sum, r) => sum + r.durationSeconds, 0) / 60;
+		rpc.notify.warning({
+			title: `${recordings.length} interrupted recording${recordings.length === 1 ? '' : 's'} found`,
+			description: `About ${totalMinutes.toFixed(1)} minutes of audio in ${outputFolder} was not saved properly, most likely because the app closed unexpectedly.`,
+			action: {
+				type: 'button',
+				label: 'Recover',
+				onClick: () => recoverRecordings(recordings),
+			},
+			persist: true,
+		});
+	} catch (error) {
+		rpc.notify.error({
+			title: 'Failed to check for interrupted recordings',
+			description: extractErrorMessage(error),
+		});
+	}
+}
+
+async function recoverRecordings(recordings: RecoverableRecording[]) {
+	const failed: string[] = [];
+	for (const recording of recordings) {
+		try {
+			await invoke('recover_recording', { filePath: recording.filePath });
+		} catch (error) {
+			failed.push(`${recording.filePath}: ${extractErrorMessage(error)}`);
+		}
+	}
+
+	if (failed.length > 0) {
+		rpc.notify.error({
+			title: `Failed to recover ${failed.length} of ${recordings.length} recordings`,
+			description: failed.join('\n'),
+		});
+		return;
+	}
+	rpc.notify.success({
+		title: 'Recordings recovered',
+		description: `Repaired ${recordings.length} recording${recordings.length === 1 ? '' : 's'}; the files are in your recordings folder.`,
+	});
+}